@@ -1,25 +1,40 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, io::IsTerminal, path::PathBuf, str::FromStr, time::Duration};
 
-use crate::{process_csv, CmdExector};
-use clap::Parser;
+use crate::{
+    check_date_format, collect_batch_inputs, is_batch_input, process_csv, process_csv_batch,
+    process_csv_decrypt, process_csv_diff, process_csv_encrypt, process_csv_fake, process_csv_mask,
+    process_csv_reformat, process_csv_render, process_csv_sample, process_csv_slice,
+    process_csv_watch, process_text_layout, BatchStatus, CmdExector, DateNormalizer,
+    ReformatOptions, TextLayout,
+};
+use chrono_tz::Tz;
+use clap::{ArgGroup, Parser};
+use enum_dispatch::enum_dispatch;
 
 // 使用上层的包
-use super::verify_file;
+use super::{verify_file, verify_input};
 
 #[derive(Debug, Clone, Copy)]
 
 pub enum OutputFormat {
     Json,
     Yaml,
+    Csv,
     // 其他格式的处理
 }
 
 // CsvOpts 作为enum的负载，我们需要实现一个符合 我们描述的 CSV 命令行相关的参数
+// 不带子命令时为原本的格式转换 `rcli csv -i ...`，带子命令时 (例如 `rcli csv diff`) 转换相关的参数不再是必填项
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpts {
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+
     // 定义一个参数，为必填项， 支持短参数，长参数，以及提示
-    #[arg(short, long, value_parser = verify_file)]
-    pub input: String,
+    // 也可以是一个目录或者 glob，例如 -i 'data/**/*.csv'，此时转换目录下所有的 csv 文件
+    #[arg(short, long, value_parser = verify_input, required = true)]
+    pub input: Option<String>,
     // 定义输出路径 // default_value 是实现了一个 From Trait 的因此，如果你返回的东西是直接返回字面量，就使用 default_value_t
     // 默认使用了 "output.json".info() 将 &str convert成了 String::from("output.json") 的这样一个堆内存变量
     // #[arg(short, long, default_value = "output.json")]
@@ -37,21 +52,437 @@ pub struct CsvOpts {
     // 参见详细的提示 Short option names must be unique for each argument, but '-h' is in use by both 'header' and 'help'
     #[arg(long, default_value_t = true)]
     pub header: bool,
+
+    // 批量转换时的输出目录，目录结构与输入保持一致
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+    // 批量转换时的并发数，0 代表使用 CPU 核数
+    #[arg(short, long, default_value_t = 0)]
+    pub jobs: usize,
+    // 批量转换时，某个文件失败之后继续转换剩下的文件
+    #[arg(long)]
+    pub continue_on_error: bool,
+
+    // 输入为定长格式，例如 --fixed-width "Name:0-20,Position:20-32"
+    #[arg(long, value_parser = parse_fixed_width, conflicts_with = "regex")]
+    pub fixed_width: Option<TextLayout>,
+    // 使用正则的命名捕获组解析每一行，例如 --regex '^(?<Name>.+?)\s{2,}(?<Position>\S+)'
+    #[arg(long, value_parser = parse_regex_layout)]
+    pub regex: Option<TextLayout>,
+
+    // 监听输入文件或目录，变化之后重新转换，转换失败时继续监听
+    #[arg(long)]
+    pub watch: bool,
+    // 监听模式下，在这段时间 (毫秒) 内的多次修改只触发一次转换
+    #[arg(long, default_value_t = 300, requires = "watch")]
+    pub debounce: u64,
+
+    // 需要规范化的日期列，可以多次指定，例如 --date-column DOB
+    #[arg(long = "date-column")]
+    pub date_columns: Vec<String>,
+    // 日期列的输入格式，例如 "%b %d, %Y"，不指定时自动识别常见格式
+    #[arg(long, requires = "date_columns")]
+    pub input_format: Option<String>,
+    // 日期列的输出格式: iso8601, timestamp 或者 strftime 格式
+    #[arg(long, value_parser = parse_date_format, default_value = "iso8601")]
+    pub output_format: DateFormat,
+    // 不带时区的时间按这个时区理解，例如 Europe/Rome
+    #[arg(long, value_parser = parse_timezone, requires = "date_columns")]
+    pub from_tz: Option<Tz>,
+    // 输出时转换到这个时区，例如 UTC
+    #[arg(long, value_parser = parse_timezone, requires = "date_columns")]
+    pub to_tz: Option<Tz>,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum CsvSubCommand {
+    #[command(about = "Compare two csv files by a key column")]
+    Diff(CsvDiffOpts),
+    #[command(about = "Mask or pseudonymize personal data in csv columns")]
+    Mask(CsvMaskOpts),
+    #[command(about = "Encrypt csv columns with XChaCha20-Poly1305")]
+    Encrypt(CsvEncryptOpts),
+    #[command(about = "Decrypt csv columns encrypted by `rcli csv encrypt`")]
+    Decrypt(CsvDecryptOpts),
+    #[command(about = "Generate synthetic csv data from a yaml schema")]
+    Fake(CsvFakeOpts),
+    #[command(about = "Render csv rows with a jinja style template")]
+    Render(CsvRenderOpts),
+    #[command(about = "Rewrite a csv file of any dialect into a normalized one")]
+    Reformat(CsvReformatOpts),
+    #[command(about = "Randomly sample or slice rows of a large csv file")]
+    Sample(CsvSampleOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvDiffOpts {
+    #[arg(value_parser = verify_file)]
+    pub old: String,
+    #[arg(value_parser = verify_file)]
+    pub new: String,
+    // 用来匹配两个文件中同一行的列
+    #[arg(short, long)]
+    pub key: String,
+    #[arg(short, long, value_parser = parser_diff_format, default_value = "text")]
+    pub format: CsvDiffFormat,
+    // 终端中默认带颜色输出，重定向到文件时自动关闭
+    #[arg(long)]
+    pub no_color: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvMaskOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    // - 代表输出到 stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    // 列名=模式，可以多次指定，例如 --column Name=hash --column DOB=year-only
+    #[arg(short, long = "column", value_parser = parse_mask_column, required = true)]
+    pub columns: Vec<(String, MaskMode)>,
+    // hash 模式使用的 blake3 key 文件，可以通过 `rcli text generate` 生成
+    #[arg(short, long, value_parser = verify_file)]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvEncryptOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    // 需要加密的列，逗号分隔，例如 --columns "DOB,Kit Number"
+    #[arg(short, long, value_delimiter = ',', required = true)]
+    pub columns: Vec<String>,
+    // 32 字节的 key 文件，可以通过 `rcli text generate` 生成
+    #[arg(short, long, value_parser = verify_file)]
+    pub key: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvDecryptOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(short, long, value_delimiter = ',', required = true)]
+    pub columns: Vec<String>,
+    #[arg(short, long, value_parser = verify_file)]
+    pub key: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvFakeOpts {
+    // 描述每一列如何生成的 yaml 文件
+    #[arg(short, long, value_parser = verify_file)]
+    pub schema: String,
+    #[arg(short, long, default_value_t = 100)]
+    pub rows: usize,
+    // 指定之后每次生成的数据都相同
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(short, long, value_parser = parser_format, default_value = "csv")]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvRenderOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    #[arg(short, long, value_parser = verify_file)]
+    pub template: String,
+    // 整个数据集渲染一次时的输出，- 代表 stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    // 指定文件名模板后，每一行渲染一个文件, 例如 "{{ Name | slug }}.md"，此时使用 --output-dir 而不是 --output
+    #[arg(long, conflicts_with = "output")]
+    pub filename: Option<String>,
+    #[arg(long, default_value = ".")]
+    pub output_dir: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvReformatOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    // 输入的分隔符，不指定时根据表头自动识别 (, ; tab |)
+    #[arg(long)]
+    pub input_delimiter: Option<char>,
+    #[arg(short, long, default_value_t = ',')]
+    pub delimiter: char,
+    #[arg(long, value_parser = parse_quote_style, default_value = "minimal")]
+    pub quote: CsvQuoteStyle,
+    #[arg(long, value_parser = parse_line_terminator, default_value = "lf")]
+    pub terminator: CsvLineTerminator,
+    // 输出时写入 UTF-8 BOM
+    #[arg(long)]
+    pub bom: bool,
+    // 去掉每个字段首尾的空白
+    #[arg(long)]
+    pub trim: bool,
+    // 丢弃所有字段都为空的行
+    #[arg(long)]
+    pub drop_empty: bool,
+    // 重复的列名加上序号
+    #[arg(long)]
+    pub dedupe_headers: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("mode").required(true).args(["count", "rows"])))]
+pub struct CsvSampleOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    // 随机抽取的行数，使用蓄水池抽样只遍历一遍文件
+    #[arg(short = 'n', long)]
+    pub count: Option<usize>,
+    // 按该列的取值分层抽样，每个取值各抽 count 行
+    #[arg(long, requires = "count")]
+    pub by: Option<String>,
+    // 指定 seed 时每次抽样的结果相同
+    #[arg(long, requires = "count")]
+    pub seed: Option<u64>,
+    // 按行号截取 (不含表头，从 0 开始)，例如 --rows 5000..6000, --rows 5000..
+    #[arg(long, value_parser = parse_row_range)]
+    pub rows: Option<RowRange>,
+    // 使用 <input>.idx 中的字节偏移直接跳到起始行，索引不存在或过期时自动重建
+    #[arg(long, requires = "rows")]
+    pub index: bool,
+}
+
+/// 半开区间 [start, end)，end 为 None 时截取到文件末尾
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RowRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+/// 日期列的输出格式
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateFormat {
+    // 日期为 1990-04-18，时间为 rfc3339
+    Iso8601,
+    // unix 时间戳 (秒)
+    Timestamp,
+    // strftime 格式，例如 "%d/%m/%Y"
+    Custom(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CsvQuoteStyle {
+    Minimal,
+    All,
+    NonNumeric,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CsvLineTerminator {
+    Lf,
+    Crlf,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CsvDiffFormat {
+    Text,
+    Json,
 }
 
 impl CmdExector for CsvOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let output = if let Some(output) = self.output {
-            output
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+        let input = self
+            .input
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("--input is required"))?;
+        if self.watch {
+            let outputs: Vec<PathBuf> = if is_batch_input(&input) {
+                self.output_dir.iter().cloned().collect()
+            } else {
+                let output = self.output_path();
+                (output != "-").then(|| output.into()).into_iter().collect()
+            };
+            let debounce = Duration::from_millis(self.debounce);
+            return process_csv_watch(&input, &outputs, debounce, || self.convert(&input));
+        }
+        self.convert(&input)
+    }
+}
+
+impl CsvOpts {
+    fn output_path(&self) -> String {
+        if let Some(output) = &self.output {
+            output.clone()
         } else {
             // "output.json".into(),以{} format一个数据结构的话，那么这个数据结构就需要实现 Display Trait
             format!("output.{}", self.format)
+        }
+    }
+
+    fn convert(&self, input: &str) -> anyhow::Result<()> {
+        let layout = self.fixed_width.as_ref().or(self.regex.as_ref());
+        let dates = (!self.date_columns.is_empty()).then(|| DateNormalizer {
+            columns: self.date_columns.clone(),
+            input_format: self.input_format.clone(),
+            output_format: self.output_format.clone(),
+            from_tz: self.from_tz,
+            to_tz: self.to_tz,
+        });
+        let dates = dates.as_ref();
+        if is_batch_input(input) {
+            if layout.is_some() {
+                anyhow::bail!("--fixed-width and --regex only support a single input file");
+            }
+            let output_dir = self.output_dir.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("--output-dir is required when converting a directory or glob")
+            })?;
+            let inputs = collect_batch_inputs(input, output_dir, self.format)?;
+            let results = process_csv_batch(
+                inputs,
+                self.format,
+                self.jobs,
+                self.continue_on_error,
+                dates,
+            )?;
+            let mut failed = 0;
+            for result in &results {
+                if !matches!(result.status, BatchStatus::Ok) {
+                    failed += 1;
+                }
+                eprintln!("{}", result);
+            }
+            eprintln!(
+                "{} converted, {} failed or skipped",
+                results.len() - failed,
+                failed
+            );
+            if failed > 0 {
+                anyhow::bail!("{} of {} files were not converted", failed, results.len());
+            }
+            return Ok(());
+        }
+        let output = self.output_path();
+        match layout {
+            Some(layout) => process_text_layout(input, &output, self.format, layout, dates),
+            None => process_csv(input, &output, self.format, dates),
+        }
+    }
+}
+
+impl CmdExector for CsvMaskOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_mask(
+            &self.input,
+            &self.output,
+            &self.columns,
+            self.key.as_deref(),
+        )
+    }
+}
+
+impl CmdExector for CsvEncryptOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_encrypt(&self.input, &self.output, &self.columns, &self.key)
+    }
+}
+
+impl CmdExector for CsvDecryptOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_decrypt(&self.input, &self.output, &self.columns, &self.key)
+    }
+}
+
+impl CmdExector for CsvFakeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_fake(
+            &self.schema,
+            &self.output,
+            self.format,
+            self.rows,
+            self.seed,
+        )
+    }
+}
+
+impl CmdExector for CsvRenderOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let per_row = self
+            .filename
+            .as_deref()
+            .map(|filename| (self.output_dir.as_path(), filename));
+        process_csv_render(&self.input, &self.template, &self.output, per_row)
+    }
+}
+
+impl CmdExector for CsvSampleOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        match (self.count, self.rows) {
+            (Some(count), _) => {
+                process_csv_sample(
+                    &self.input,
+                    &self.output,
+                    count,
+                    self.by.as_deref(),
+                    self.seed,
+                )?;
+                Ok(())
+            }
+            (None, Some(rows)) => process_csv_slice(&self.input, &self.output, rows, self.index),
+            // clap 已经保证 count 和 rows 必须指定其中一个
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+impl CmdExector for CsvReformatOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let opts = ReformatOptions {
+            input_delimiter: self.input_delimiter.map(delimiter_byte).transpose()?,
+            delimiter: delimiter_byte(self.delimiter)?,
+            quote: self.quote,
+            terminator: self.terminator,
+            bom: self.bom,
+            trim: self.trim,
+            drop_empty: self.drop_empty,
+            dedupe_headers: self.dedupe_headers,
         };
-        process_csv(&self.input, &output, self.format)
+        process_csv_reformat(&self.input, &self.output, &opts)
     }
 }
 
-fn parser_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
+/// csv 的分隔符只支持单字节的字符
+fn delimiter_byte(delimiter: char) -> anyhow::Result<u8> {
+    u8::try_from(delimiter)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| anyhow::anyhow!("Delimiter must be an ascii character"))
+}
+
+impl CmdExector for CsvDiffOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let diff = process_csv_diff(&self.old, &self.new, &self.key)?;
+        match self.format {
+            CsvDiffFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+            CsvDiffFormat::Text => {
+                if self.no_color || !std::io::stdout().is_terminal() {
+                    colored::control::set_override(false);
+                }
+                println!("{}", diff);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn parser_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
     // to_lowercase 将用户输入转化成小写
     // match format.to_lowercase().as_str() {
     //     "json" => Ok(OutputFormat::JSON),
@@ -77,6 +508,7 @@ impl From<OutputFormat> for &'static str {
         match value {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::Csv => "csv",
         }
     }
 }
@@ -89,8 +521,9 @@ impl FromStr for OutputFormat {
         match s.to_lowercase().as_str() {
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
             _ => Err(anyhow::anyhow!(
-                "Unsupported format. Supported formats: json, yaml"
+                "Unsupported format. Supported formats: json, yaml, csv"
             )),
         }
     }
@@ -102,3 +535,232 @@ impl fmt::Display for OutputFormat {
         write!(f, "{}", fmt)
     }
 }
+
+fn parser_diff_format(format: &str) -> Result<CsvDiffFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for CsvDiffFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(CsvDiffFormat::Text),
+            "json" => Ok(CsvDiffFormat::Json),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported diff format. Supported formats: text, json"
+            )),
+        }
+    }
+}
+
+impl From<CsvDiffFormat> for &'static str {
+    fn from(value: CsvDiffFormat) -> Self {
+        match value {
+            CsvDiffFormat::Text => "text",
+            CsvDiffFormat::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for CsvDiffFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt = Into::<&str>::into(*self);
+        write!(f, "{}", fmt)
+    }
+}
+
+/// 脱敏的方式
+#[derive(Debug, Clone, Copy)]
+pub enum MaskMode {
+    Hash,
+    YearOnly,
+    Redact,
+}
+
+fn parse_fixed_width(spec: &str) -> Result<TextLayout, anyhow::Error> {
+    TextLayout::fixed_width(spec)
+}
+
+fn parse_regex_layout(pattern: &str) -> Result<TextLayout, anyhow::Error> {
+    TextLayout::regex(pattern)
+}
+
+fn parse_mask_column(column: &str) -> Result<(String, MaskMode), anyhow::Error> {
+    let (name, mode) = column
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected <column>=<mode>, e.g. Name=hash"))?;
+    Ok((name.to_string(), mode.parse()?))
+}
+
+impl FromStr for MaskMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hash" => Ok(MaskMode::Hash),
+            "year-only" => Ok(MaskMode::YearOnly),
+            "redact" => Ok(MaskMode::Redact),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported mask mode. Supported modes: hash, year-only, redact"
+            )),
+        }
+    }
+}
+
+impl From<MaskMode> for &'static str {
+    fn from(value: MaskMode) -> Self {
+        match value {
+            MaskMode::Hash => "hash",
+            MaskMode::YearOnly => "year-only",
+            MaskMode::Redact => "redact",
+        }
+    }
+}
+
+impl fmt::Display for MaskMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt = Into::<&str>::into(*self);
+        write!(f, "{}", fmt)
+    }
+}
+
+fn parse_date_format(format: &str) -> Result<DateFormat, anyhow::Error> {
+    format.parse()
+}
+
+fn parse_timezone(tz: &str) -> Result<Tz, anyhow::Error> {
+    tz.parse()
+        .map_err(|_| anyhow::anyhow!("Unknown timezone `{}`, e.g. UTC or Europe/Rome", tz))
+}
+
+impl FromStr for DateFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "iso8601" => Ok(DateFormat::Iso8601),
+            "timestamp" => Ok(DateFormat::Timestamp),
+            _ if s.contains('%') => {
+                check_date_format(s)?;
+                Ok(DateFormat::Custom(s.to_string()))
+            }
+            _ => Err(anyhow::anyhow!(
+                "Unsupported date format. Supported formats: iso8601, timestamp or a strftime format"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateFormat::Iso8601 => write!(f, "iso8601"),
+            DateFormat::Timestamp => write!(f, "timestamp"),
+            DateFormat::Custom(format) => write!(f, "{}", format),
+        }
+    }
+}
+
+fn parse_quote_style(quote: &str) -> Result<CsvQuoteStyle, anyhow::Error> {
+    quote.parse()
+}
+
+impl FromStr for CsvQuoteStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "minimal" => Ok(CsvQuoteStyle::Minimal),
+            "all" => Ok(CsvQuoteStyle::All),
+            "non-numeric" => Ok(CsvQuoteStyle::NonNumeric),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported quote style. Supported styles: minimal, all, non-numeric"
+            )),
+        }
+    }
+}
+
+impl From<CsvQuoteStyle> for &'static str {
+    fn from(value: CsvQuoteStyle) -> Self {
+        match value {
+            CsvQuoteStyle::Minimal => "minimal",
+            CsvQuoteStyle::All => "all",
+            CsvQuoteStyle::NonNumeric => "non-numeric",
+        }
+    }
+}
+
+impl fmt::Display for CsvQuoteStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt = Into::<&str>::into(*self);
+        write!(f, "{}", fmt)
+    }
+}
+
+fn parse_line_terminator(terminator: &str) -> Result<CsvLineTerminator, anyhow::Error> {
+    terminator.parse()
+}
+
+impl FromStr for CsvLineTerminator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lf" => Ok(CsvLineTerminator::Lf),
+            "crlf" => Ok(CsvLineTerminator::Crlf),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported line terminator. Supported terminators: lf, crlf"
+            )),
+        }
+    }
+}
+
+impl From<CsvLineTerminator> for &'static str {
+    fn from(value: CsvLineTerminator) -> Self {
+        match value {
+            CsvLineTerminator::Lf => "lf",
+            CsvLineTerminator::Crlf => "crlf",
+        }
+    }
+}
+
+impl fmt::Display for CsvLineTerminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt = Into::<&str>::into(*self);
+        write!(f, "{}", fmt)
+    }
+}
+
+fn parse_row_range(range: &str) -> Result<RowRange, anyhow::Error> {
+    range.parse()
+}
+
+impl FromStr for RowRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| anyhow::anyhow!("Expected <start>..<end>, e.g. 5000..6000"))?;
+        let start = if start.is_empty() { 0 } else { start.parse()? };
+        let end = if end.is_empty() {
+            None
+        } else {
+            Some(end.parse()?)
+        };
+        if end.is_some_and(|end| end < start) {
+            anyhow::bail!("Row range is empty: {}", s);
+        }
+        Ok(RowRange { start, end })
+    }
+}
+
+impl fmt::Display for RowRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}..{}", self.start, end),
+            None => write!(f, "{}..", self.start),
+        }
+    }
+}
@@ -1,38 +1,445 @@
-use clap::Parser;
+use clap::{ArgAction, Args, Parser};
+use enum_dispatch::enum_dispatch;
+use std::{fmt, io::Write, str::FromStr};
 
-use crate::{process_genpass, CmdExector};
-use zxcvbn::zxcvbn; //zxcvbn 密码强��性校验工具
+use super::{read_secret, verify_file};
+use crate::{
+    load_policy, load_wordlist, parse_passwordrules, process_genbatch, process_genderive,
+    process_genpass, process_genpass_check, process_genpattern, process_genphrase, process_genpin,
+    process_genpronounceable, write_passwords_csv, write_passwords_json, CmdExector, DeriveOptions,
+    GeneratedPassword, PassphraseOptions, PasswordPolicy, PasswordReport, PasswordRules, PwnedDb,
+    StrengthTarget,
+};
 
-#[derive(Debug, Parser)]
-pub struct GenPassOpt {
-    #[arg(short, long, default_value_t = 16)] // 16 是一个默认值，表示生成的密码的长度
-    pub length: u8,
+// 字符密码的规则，genpass 和 genpass derive 共用
+#[derive(Debug, Args)]
+pub struct PasswordRuleArgs {
+    // 生成的密码的长度，默认为 16，使用 --policy 时默认为策略中的长度
+    #[arg(short, long)]
+    pub length: Option<u8>,
 
-    #[arg(long, default_value_t = true)] // 默认支持大写字母
+    // 默认支持大写字母，--no-upper 关闭
+    // default_value_t = true 的 bool 参数无法关闭，因此使用 SetFalse
+    #[arg(long = "no-upper", action = ArgAction::SetFalse, overrides_with = "legacy_uppercase")]
     pub uppercase: bool,
 
-    #[arg(long, default_value_t = true)] // 默认支持小写字母
+    #[arg(long = "no-lower", action = ArgAction::SetFalse, overrides_with = "legacy_lowercase")]
+    // 默认支持小写字母
     pub lowercase: bool,
 
-    #[arg(long, default_value_t = true)] // 默认支持数字
+    #[arg(long = "no-numbers", action = ArgAction::SetFalse, overrides_with = "legacy_numbers")]
+    // 默认支持数字
     pub numbers: bool,
 
-    #[arg(long, default_value_t = true)] // 默认支持特殊字符
+    #[arg(long = "no-symbols", action = ArgAction::SetFalse, overrides_with = "legacy_symbols")]
+    // 默认支持特殊字符
     pub symbols: bool,
+
+    // 兼容旧版本的 --uppercase / --lowercase / --numbers / --symbols，字符类默认就是开启的
+    // 与 --no-* 同时使用时以后出现的为准
+    #[arg(long = "uppercase", hide = true, overrides_with = "uppercase")]
+    pub legacy_uppercase: bool,
+    #[arg(long = "lowercase", hide = true, overrides_with = "lowercase")]
+    pub legacy_lowercase: bool,
+    #[arg(long = "numbers", hide = true, overrides_with = "numbers")]
+    pub legacy_numbers: bool,
+    #[arg(long = "symbols", hide = true, overrides_with = "symbols")]
+    pub legacy_symbols: bool,
+
+    // 使用命名的密码策略，例如 --policy aws-iam，生成的密码都会按策略检查
+    #[arg(long)]
+    pub policy: Option<String>,
+    // 自定义的策略文件 (yaml)，其中的策略会覆盖同名的内置策略
+    #[arg(long, value_parser = verify_file, requires = "policy")]
+    pub policy_file: Option<String>,
+    // 网站提供的 Apple passwordrules 格式的规则，例如 --rules "minlength: 20; required: lower; allowed: [-_];"
+    #[arg(long, conflicts_with = "policy")]
+    pub rules: Option<String>,
+
+    // 自定义特殊字符集，例如 --symbols-set '-_.'
+    #[arg(long, allow_hyphen_values = true)]
+    pub symbols_set: Option<String>,
+    // 不使用的字符，例如 --exclude 'il1'
+    #[arg(long, default_value = "", allow_hyphen_values = true)]
+    pub exclude: String,
+    // 默认不使用容易混淆的字符 (I O l 0)
+    #[arg(long)]
+    pub include_ambiguous: bool,
+    // 每一类字符至少出现的次数
+    #[arg(long, default_value_t = 0)]
+    pub min_upper: usize,
+    #[arg(long, default_value_t = 0)]
+    pub min_lower: usize,
+    #[arg(long, default_value_t = 0)]
+    pub min_digits: usize,
+    #[arg(long, default_value_t = 0)]
+    pub min_symbols: usize,
+}
+
+impl PasswordRuleArgs {
+    /// 按规则生成一个字符密码，指定了策略时生成的密码也符合策略
+    pub(crate) fn generate(&self) -> anyhow::Result<String> {
+        let (rules, policy) = self.resolve()?;
+        match policy {
+            Some(policy) => policy.generate(&rules),
+            None => process_genpass(&rules),
+        }
+    }
+
+    /// 转换成 PasswordRules，指定了 --policy / --rules 时合并策略并返回
+    fn resolve(&self) -> anyhow::Result<(PasswordRules, Option<PasswordPolicy>)> {
+        let length = self.length.map(usize::from);
+        let mut rules = PasswordRules {
+            length: length.unwrap_or(16),
+            upper: self.uppercase,
+            lower: self.lowercase,
+            number: self.numbers,
+            symbol: self.symbols,
+            symbols_set: self.symbols_set.clone(),
+            exclude: self.exclude.clone(),
+            include_ambiguous: self.include_ambiguous,
+            min_upper: self.min_upper,
+            min_lower: self.min_lower,
+            min_digits: self.min_digits,
+            min_symbols: self.min_symbols,
+        };
+        let policy = match (&self.policy, &self.rules) {
+            (Some(name), _) => Some(load_policy(name, self.policy_file.as_deref())?),
+            (None, Some(rules)) => Some(parse_passwordrules(rules)?),
+            (None, None) => None,
+        };
+        if let Some(policy) = &policy {
+            policy.apply(&mut rules, length)?;
+        }
+        Ok((rules, policy))
+    }
+}
+
+// 不带子命令时生成密码，带子命令时 (例如 `rcli genpass check`) 生成相关的参数不再使用
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct GenPassOpt {
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
+    #[command(flatten)]
+    pub rule_args: PasswordRuleArgs,
+
+    // 生成 diceware 口令而不是字符密码，指定单词的数量，例如 --words 6
+    // 内置的 BIP-39 单词表每个单词只有 11 bit (EFF 大单词表约 12.9 bit)，熵以实际使用的单词表计算
+    // 口令不使用字符类的规则，因此不能与 --policy / --rules 一起使用
+    #[arg(long, conflicts_with_all = ["policy", "rules"])]
+    pub words: Option<usize>,
+    // 自定义单词表，每行一个单词，兼容 EFF 单词表的格式，默认使用内置的 BIP-39 英文单词表
+    #[arg(long, value_parser = verify_file, requires = "words")]
+    pub wordlist: Option<String>,
+    // 单词之间的分隔符
+    #[arg(long, default_value = "-", requires = "words")]
+    pub separator: String,
+    // 每个单词首字母大写
+    #[arg(long, requires = "words")]
+    pub capitalize: bool,
+    // 在口令中插入一个数字
+    #[arg(long, requires = "words")]
+    pub insert_digit: bool,
+    // 在口令中插入一个特殊字符
+    #[arg(long, requires = "words")]
+    pub insert_symbol: bool,
+
+    // 按模板生成，A 大写字母, a 小写字母, 9 数字, ! 特殊字符, ? 任意字符，其他字符原样输出
+    // 例如 --pattern "Aaaa-9999-!!??"
+    #[arg(long, conflicts_with_all = ["words", "policy", "rules"])]
+    pub pattern: Option<String>,
+    // 生成指定位数的数字 PIN，重复、顺序、生日以及常见的 PIN 会被排除
+    #[arg(long, conflicts_with_all = ["words", "policy", "rules", "pattern"])]
+    pub pin: Option<usize>,
+    // 生成由辅音 + 元音音节组成、方便读出来的密码，数字和特殊字符追加在后面
+    #[arg(long, conflicts_with_all = ["words", "policy", "rules", "pattern", "pin"])]
+    pub pronounceable: bool,
+
+    // 批量生成互不相同的密码，例如 -n 500
+    #[arg(short = 'n', long, default_value_t = 1)]
+    pub count: usize,
+    // 输出格式: plain (每行一个密码), json 或 csv，json / csv 中包含评分、guesses_log10 和熵
+    #[arg(long, value_parser = parse_genpass_format, default_value = "plain")]
+    pub format: GenPassFormat,
+
+    // 生成的密码至少达到的 zxcvbn 评分 (0 - 4)，达不到时重新生成
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4), default_value_t = 0)]
+    pub min_score: u8,
+    // 生成的密码至少达到的熵 (bit)，按实际的字符集大小和长度计算，达不到时直接返回错误
+    #[arg(long, default_value_t = 0.0)]
+    pub min_entropy: f64,
+    // 本地的 HIBP 密码库 (按 SHA1 排序的 pwned-passwords-sha1 文件)，出现在其中的密码会被重新生成
+    #[arg(long, value_parser = verify_file)]
+    pub pwned_db: Option<String>,
 }
 
 impl CmdExector for GenPassOpt {
     async fn execute(self) -> anyhow::Result<()> {
-        let pass = process_genpass(
-            self.length,
-            self.uppercase,
-            self.lowercase,
-            self.numbers,
-            self.symbols,
-        )?;
-        println!("{}", pass);
-        let estimate = zxcvbn(&pass, &[]);
-        eprintln!("Password strength: {}", estimate.score()); // 3
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+        let count = self.count;
+        let format = self.format;
+        let passwords = self.generate(count)?;
+        match format {
+            GenPassFormat::Plain if passwords.len() == 1 => {
+                let password = &passwords[0];
+                println!("{}", password.password);
+                eprintln!(
+                    "Password strength: {}, entropy: {:.1} bits",
+                    password.score, password.entropy_bits
+                );
+            }
+            GenPassFormat::Plain => {
+                let mut stdout = std::io::stdout().lock();
+                for password in &passwords {
+                    writeln!(stdout, "{}", password.password)?;
+                }
+            }
+            GenPassFormat::Json => {
+                write_passwords_json(&passwords, std::io::stdout().lock())?;
+                println!();
+            }
+            GenPassFormat::Csv => write_passwords_csv(&passwords, std::io::stdout().lock())?,
+        }
         Ok(()) // 0 代表没有任何错误
     }
 }
+
+impl GenPassOpt {
+    /// 按参数选择生成方式，生成 count 个互不相同的密码
+    fn generate(self, count: usize) -> anyhow::Result<Vec<GeneratedPassword>> {
+        let target = StrengthTarget {
+            min_score: self.min_score,
+            min_entropy: self.min_entropy,
+            pwned_db: self.pwned_db.as_deref().map(PwnedDb::open).transpose()?,
+        };
+        if let Some(words) = self.words {
+            let wordlist = load_wordlist(self.wordlist.as_deref())?;
+            let opts = PassphraseOptions {
+                words,
+                separator: self.separator,
+                capitalize: self.capitalize,
+                digit: self.insert_digit,
+                symbol: self.insert_symbol,
+            };
+            return process_genbatch(count, &target, || process_genphrase(&wordlist, &opts));
+        }
+        if let Some(length) = self.pin {
+            return process_genbatch(count, &target, || process_genpin(length));
+        }
+        let (rules, policy) = self.rule_args.resolve()?;
+        if self.pronounceable {
+            return process_genbatch(count, &target, || process_genpronounceable(&rules));
+        }
+        if let Some(pattern) = &self.pattern {
+            return process_genbatch(count, &target, || process_genpattern(pattern, &rules));
+        }
+        let entropy = rules.entropy()?;
+        process_genbatch(count, &target, || {
+            let password = match &policy {
+                Some(policy) => policy.generate(&rules)?,
+                None => process_genpass(&rules)?,
+            };
+            Ok((password, entropy))
+        })
+    }
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum GenPassSubCommand {
+    #[command(about = "Analyze existing passwords with zxcvbn")]
+    Check(GenPassCheckOpts),
+    #[command(about = "Derive a site password from a master password, nothing is stored")]
+    Derive(GenPassDeriveOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassCheckOpts {
+    // 每行一个密码，默认从 stdin 读取，避免密码出现在 shell 历史中
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    // 用户名、网站名等容易被猜到的单词，可以多次指定，例如 --user-input jsmith
+    #[arg(long = "user-input")]
+    pub user_inputs: Vec<String>,
+    // 默认不输出密码，只输出行号
+    #[arg(long)]
+    pub show: bool,
+    // 输出格式: plain 或 json
+    #[arg(long, value_parser = parse_genpass_format, default_value = "plain")]
+    pub format: GenPassFormat,
+    // 有密码的评分低于这个值时返回非 0 的退出码，用于 CI 检查
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub min_score: Option<u8>,
+    // 本地的 HIBP 密码库 (按 SHA1 排序的 pwned-passwords-sha1 文件)，不会访问网络
+    // 有密码出现在其中时返回非 0 的退出码
+    #[arg(long, value_parser = verify_file)]
+    pub pwned_db: Option<String>,
+}
+
+impl CmdExector for GenPassCheckOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let pwned_db = self.pwned_db.as_deref().map(PwnedDb::open).transpose()?;
+        let reports =
+            process_genpass_check(&self.input, &self.user_inputs, self.show, pwned_db.as_ref())?;
+        match self.format {
+            GenPassFormat::Plain => {
+                let mut stdout = std::io::stdout().lock();
+                for report in &reports {
+                    write_report(&mut stdout, report)?;
+                }
+            }
+            GenPassFormat::Json => println!("{}", serde_json::to_string_pretty(&reports)?),
+            GenPassFormat::Csv => anyhow::bail!("`genpass check` supports plain and json output"),
+        }
+        let pwned = reports.iter().filter(|r| r.pwned > Some(0)).count();
+        if pwned > 0 {
+            anyhow::bail!(
+                "{} of {} passwords appear in the pwned passwords file",
+                pwned,
+                reports.len()
+            );
+        }
+        if let Some(min) = self.min_score {
+            let weak = reports.iter().filter(|r| r.score < min).count();
+            if weak > 0 {
+                anyhow::bail!(
+                    "{} of {} passwords scored below {}",
+                    weak,
+                    reports.len(),
+                    min
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassDeriveOpts {
+    // 站点，例如 example.com，不区分大小写
+    #[arg(long)]
+    pub site: String,
+    // 站点上的登录名
+    #[arg(long)]
+    pub login: String,
+    // 需要更换密码时加一
+    #[arg(long, default_value_t = 1)]
+    pub counter: u32,
+    // 与 genpass --pattern 相同格式的模板，例如 --template "Aaaa-9999-!!??"
+    #[arg(long, conflicts_with_all = ["policy", "rules"])]
+    pub template: Option<String>,
+    // Argon2id 使用的内存 (KiB)，修改之后派生出来的密码都会变化
+    #[arg(long, default_value_t = 64 * 1024)]
+    pub memory: u32,
+    // Argon2id 的迭代次数，修改之后派生出来的密码都会变化
+    #[arg(long, default_value_t = 3)]
+    pub iterations: u32,
+    // 从 stdin 的第一行读取主密码，而不是在终端中输入，用于脚本
+    #[arg(long)]
+    pub master_stdin: bool,
+
+    #[command(flatten)]
+    pub rule_args: PasswordRuleArgs,
+}
+
+impl CmdExector for GenPassDeriveOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let (rules, policy) = self.rule_args.resolve()?;
+        let master = read_secret("Master password: ", self.master_stdin)?;
+        let opts = DeriveOptions {
+            site: self.site,
+            login: self.login,
+            counter: self.counter,
+            template: self.template,
+            memory_kib: self.memory,
+            iterations: self.iterations,
+        };
+        let password = process_genderive(&master, &opts, &rules, policy.as_ref())?;
+        println!("{}", password);
+        let estimate = zxcvbn::zxcvbn(&password, &[]);
+        eprintln!("Password strength: {}", estimate.score());
+        Ok(())
+    }
+}
+
+fn write_report(mut writer: impl Write, report: &PasswordReport) -> anyhow::Result<()> {
+    match &report.password {
+        Some(password) => write!(writer, "line {}: {}", report.line, password)?,
+        None => write!(writer, "line {}", report.line)?,
+    }
+    writeln!(
+        writer,
+        ", score {}/4, guesses 10^{:.2}",
+        report.score, report.guesses_log10
+    )?;
+    let times = &report.crack_times;
+    writeln!(
+        writer,
+        "  crack time: online throttled {}, online {}, offline slow hash {}, offline fast hash {}",
+        times.online_throttling_100_per_hour,
+        times.online_no_throttling_10_per_second,
+        times.offline_slow_hashing_1e4_per_second,
+        times.offline_fast_hashing_1e10_per_second
+    )?;
+    writeln!(writer, "  patterns: {}", report.patterns.join(", "))?;
+    match report.pwned {
+        Some(0) => writeln!(writer, "  pwned: not found")?,
+        Some(count) => writeln!(writer, "  pwned: seen {} times in data breaches", count)?,
+        None => {}
+    }
+    if let Some(warning) = &report.warning {
+        writeln!(writer, "  warning: {}", warning)?;
+    }
+    for suggestion in &report.suggestions {
+        writeln!(writer, "  suggestion: {}", suggestion)?;
+    }
+    Ok(())
+}
+
+/// genpass 的输出格式
+#[derive(Debug, Clone, Copy)]
+pub enum GenPassFormat {
+    // 每行一个密码，只生成一个时强度输出到 stderr
+    Plain,
+    Json,
+    Csv,
+}
+
+fn parse_genpass_format(format: &str) -> Result<GenPassFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl From<GenPassFormat> for &'static str {
+    fn from(value: GenPassFormat) -> Self {
+        match value {
+            GenPassFormat::Plain => "plain",
+            GenPassFormat::Json => "json",
+            GenPassFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for GenPassFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(GenPassFormat::Plain),
+            "json" => Ok(GenPassFormat::Json),
+            "csv" => Ok(GenPassFormat::Csv),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported format. Supported formats: plain, json, csv"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GenPassFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
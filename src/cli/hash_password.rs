@@ -0,0 +1,156 @@
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::{fmt, str::FromStr};
+
+use super::{read_secret, PasswordRuleArgs};
+use crate::{process_hash_password, process_verify_password, CmdExector, HashParams};
+
+// 不带子命令时计算哈希 `rcli hash-password -a bcrypt`，带子命令时校验 `rcli hash-password verify --hash ...`
+// argon2id / scrypt / pbkdf2 输出 PHC 字符串，bcrypt 输出 $2b$ 开头的 MCF 格式 (不是 PHC)
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct HashPasswordOpts {
+    #[command(subcommand)]
+    pub cmd: Option<HashPasswordSubCommand>,
+
+    // 哈希算法: argon2id / scrypt / pbkdf2 输出 PHC 字符串，bcrypt 输出 $2b$ MCF 格式
+    #[arg(short, long, value_parser = parse_hash_algorithm, default_value = "argon2id")]
+    pub algorithm: HashAlgorithm,
+    // argon2id 的内存 (KiB)
+    #[arg(long)]
+    pub memory: Option<u32>,
+    // argon2id 的迭代次数，或者 pbkdf2 的轮数
+    #[arg(long)]
+    pub iterations: Option<u32>,
+    // argon2id 和 scrypt 的并行度
+    #[arg(long)]
+    pub parallelism: Option<u32>,
+    // bcrypt 的 cost (4-31)
+    #[arg(long)]
+    pub cost: Option<u32>,
+    // scrypt 的 log2(N) 和 r
+    #[arg(long)]
+    pub log_n: Option<u8>,
+    #[arg(long)]
+    pub block_size: Option<u32>,
+
+    // 从 stdin 读取一行作为密码，而不是在终端中输入，用于脚本
+    #[arg(long)]
+    pub password_stdin: bool,
+    // 使用 genpass 的规则生成密码，以 JSON 输出密码和哈希
+    #[arg(long, conflicts_with = "password_stdin")]
+    pub generate: bool,
+    // --generate 时使用的规则
+    #[command(flatten)]
+    pub rule_args: PasswordRuleArgs,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum HashPasswordSubCommand {
+    #[command(about = "Verify a password against a PHC or bcrypt hash")]
+    Verify(HashVerifyOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct HashVerifyOpts {
+    // PHC 字符串 ($argon2id$..., $scrypt$..., $pbkdf2-sha256$...) 或者 bcrypt 哈希
+    #[arg(long)]
+    pub hash: String,
+    #[arg(long)]
+    pub password_stdin: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum HashAlgorithm {
+    Argon2id,
+    Bcrypt,
+    Scrypt,
+    Pbkdf2,
+}
+
+fn parse_hash_algorithm(algorithm: &str) -> Result<HashAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "argon2id" => Ok(HashAlgorithm::Argon2id),
+            "bcrypt" => Ok(HashAlgorithm::Bcrypt),
+            "scrypt" => Ok(HashAlgorithm::Scrypt),
+            "pbkdf2" | "pbkdf2-sha256" => Ok(HashAlgorithm::Pbkdf2),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported algorithm. Supported algorithms: argon2id, bcrypt, scrypt, pbkdf2"
+            )),
+        }
+    }
+}
+
+impl From<HashAlgorithm> for &'static str {
+    fn from(value: HashAlgorithm) -> Self {
+        match value {
+            HashAlgorithm::Argon2id => "argon2id",
+            HashAlgorithm::Bcrypt => "bcrypt",
+            HashAlgorithm::Scrypt => "scrypt",
+            HashAlgorithm::Pbkdf2 => "pbkdf2-sha256",
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt = Into::<&str>::into(*self);
+        write!(f, "{}", fmt)
+    }
+}
+
+impl CmdExector for HashPasswordOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+        let params = HashParams {
+            memory_kib: self.memory,
+            iterations: self.iterations,
+            parallelism: self.parallelism,
+            cost: self.cost,
+            log_n: self.log_n,
+            block_size: self.block_size,
+        };
+        if self.generate {
+            let password = self.rule_args.generate()?;
+            let hash = process_hash_password(&password, self.algorithm, &params)?;
+            let output = serde_json::json!({
+                "password": password,
+                "algorithm": self.algorithm.to_string(),
+                "hash": hash,
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+            return Ok(());
+        }
+
+        let password = read_secret("Password: ", self.password_stdin)?;
+        if password.is_empty() {
+            anyhow::bail!("Password must not be empty");
+        }
+        println!(
+            "{}",
+            process_hash_password(&password, self.algorithm, &params)?
+        );
+        Ok(())
+    }
+}
+
+impl CmdExector for HashVerifyOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let password = read_secret("Password: ", self.password_stdin)?;
+        if !process_verify_password(&password, &self.hash)? {
+            anyhow::bail!("Password does not match the hash");
+        }
+        eprintln!("Password matches the hash");
+        Ok(())
+    }
+}
@@ -1,15 +1,20 @@
 mod base64;
 mod csv;
 mod genpass;
+mod hash_password;
 mod http;
+mod otp;
 mod text;
+mod vault;
 
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 use std::path::{Path, PathBuf};
 
 // 这里使用seft::csv 是因为，我们使用了一个 csv create 因此如果不指定seft，就有可能和通用的create里面的名字冲突
-pub use self::{base64::*, csv::*, genpass::*, http::*, text::*};
+pub use self::{
+    base64::*, csv::*, genpass::*, hash_password::*, http::*, otp::*, text::*, vault::*,
+};
 
 #[derive(Debug, Parser)]
 #[command(name = "rcli", version, author, about, long_about = None)]
@@ -25,10 +30,20 @@ pub enum SubCommand {
         name = "csv",
         about = "Show CSV files, or convert csv to other formats"
     )]
-    Csv(CsvOpts),
+    // CsvOpts 的参数较多，放在 Box 中避免 SubCommand 过大
+    Csv(Box<CsvOpts>),
 
-    #[command(name = "genpass", about = "Generate a password")]
-    GenPass(GenPassOpt),
+    #[command(
+        name = "genpass",
+        about = "Generate a password, or check existing ones"
+    )]
+    GenPass(Box<GenPassOpt>),
+
+    #[command(
+        name = "hash-password",
+        about = "Hash a password (PHC string, or $2b$ MCF for bcrypt), or verify one"
+    )]
+    HashPassword(Box<HashPasswordOpts>),
 
     #[command(name = "base64", subcommand, about = "base64 encode and decode")]
     Base64(Base64SubCommand),
@@ -38,6 +53,12 @@ pub enum SubCommand {
 
     #[command(name = "http", subcommand)]
     Http(HttpSubCommand),
+
+    #[command(name = "otp", subcommand, about = "TOTP/HOTP one-time passwords")]
+    Otp(OtpSubCommand),
+
+    #[command(name = "vault", subcommand, about = "Encrypted local password vault")]
+    Vault(VaultSubCommand),
 }
 
 /**
@@ -52,6 +73,26 @@ fn verify_file(filename: &str) -> Result<String, String> {
     }
 }
 
+/// 读取密码等敏感输入，from_stdin 时读取 stdin 的一行 (用于脚本)，否则在终端中输入且不回显
+fn read_secret(prompt: &str, from_stdin: bool) -> anyhow::Result<String> {
+    if from_stdin {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    } else {
+        Ok(rpassword::prompt_password(prompt)?)
+    }
+}
+
+/// 批量处理时的输入，可以是文件、目录，或者 glob (例如 'data/**/*.csv')，glob 交给后续匹配时再校验
+fn verify_input(input: &str) -> Result<String, String> {
+    if input.contains(['*', '?', '[']) || Path::new(input).exists() {
+        Ok(input.into())
+    } else {
+        Err("File or directory does not exist.".into())
+    }
+}
+
 /// 用户校验用户输入的路径，是否为一个正确的路径
 fn verify_path(filename: &str) -> Result<PathBuf, String> {
     let p = Path::new(filename);
@@ -77,4 +118,31 @@ mod tests {
             Err("File does not exist.".into())
         );
     }
+
+    #[test]
+    fn test_verify_input() {
+        assert_eq!(verify_input("assets"), Ok("assets".into()));
+        assert_eq!(verify_input("data/**/*.csv"), Ok("data/**/*.csv".into()));
+        assert!(verify_input("non_existent_dir").is_err());
+    }
+
+    #[test]
+    fn test_genpass_legacy_flags() {
+        // 旧版本的 --uppercase 等参数仍然可以使用
+        let opts = Opts::try_parse_from(["rcli", "genpass", "--uppercase", "--symbols"]).unwrap();
+        let SubCommand::GenPass(opts) = opts.cmd else {
+            panic!("expect genpass");
+        };
+        assert!(opts.rule_args.uppercase && opts.rule_args.symbols);
+
+        let opts = Opts::try_parse_from(["rcli", "genpass", "--symbols", "--no-symbols"]).unwrap();
+        let SubCommand::GenPass(opts) = opts.cmd else {
+            panic!("expect genpass");
+        };
+        assert!(!opts.rule_args.symbols);
+        assert!(
+            Opts::try_parse_from(["rcli", "genpass", "--words", "4", "--policy", "aws-iam"])
+                .is_err()
+        );
+    }
 }
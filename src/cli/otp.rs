@@ -0,0 +1,216 @@
+use clap::{Args, Parser};
+use enum_dispatch::enum_dispatch;
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    process_hotp, process_hotp_verify, process_otp_secret, process_otp_uri, process_totp,
+    process_totp_verify, render_qr, CmdExector, OtpParams, MAX_OTP_WINDOW,
+};
+
+/// 一次性密码 (RFC 4226 HOTP / RFC 6238 TOTP)，用于测试 2FA 流程
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum OtpSubCommand {
+    #[command(about = "Generate a random base32 secret")]
+    Secret(OtpSecretOpts),
+    #[command(about = "Print an otpauth:// URI, optionally as a QR code")]
+    Uri(OtpUriOpts),
+    #[command(about = "Compute the current TOTP code, or a HOTP code with --counter")]
+    Code(OtpCodeOpts),
+    #[command(about = "Verify a TOTP or HOTP code")]
+    Verify(OtpVerifyOpts),
+}
+
+// uri / code / verify 共用的参数，需要与验证方保持一致
+#[derive(Debug, Args)]
+pub struct OtpArgs {
+    // base32 编码的密钥，可以包含空格和小写字母
+    #[arg(short, long)]
+    pub secret: String,
+    #[arg(short, long, value_parser = parse_otp_algorithm, default_value = "sha1")]
+    pub algorithm: OtpAlgorithm,
+    // 验证码的位数
+    #[arg(short, long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(6..=10))]
+    pub digits: u32,
+    // TOTP 的时间步长 (秒)
+    #[arg(short, long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    pub period: u64,
+}
+
+impl OtpArgs {
+    fn params(&self) -> OtpParams {
+        OtpParams {
+            algorithm: self.algorithm,
+            digits: self.digits,
+            period: self.period,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpSecretOpts {
+    // 密钥的字节数，默认 20 字节 (160 bit)
+    #[arg(short, long, default_value_t = 20, value_parser = clap::value_parser!(u16).range(10..=128))]
+    pub bytes: u16,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpUriOpts {
+    #[command(flatten)]
+    pub otp: OtpArgs,
+    // 账号，例如邮箱
+    #[arg(long)]
+    pub account: String,
+    #[arg(long)]
+    pub issuer: Option<String>,
+    // 指定时生成 HOTP 的 URI，否则为 TOTP
+    #[arg(long)]
+    pub counter: Option<u64>,
+    // 在终端中显示二维码，可以直接用 Authenticator 应用扫描
+    #[arg(long)]
+    pub qr: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpCodeOpts {
+    #[command(flatten)]
+    pub otp: OtpArgs,
+    // 指定时计算 HOTP，否则计算 TOTP
+    #[arg(long)]
+    pub counter: Option<u64>,
+    // TOTP 使用的 Unix 时间 (秒)，默认为当前时间
+    #[arg(long, conflicts_with = "counter")]
+    pub time: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpVerifyOpts {
+    #[command(flatten)]
+    pub otp: OtpArgs,
+    #[arg(short, long)]
+    pub code: String,
+    // 指定时校验 HOTP，向后查找 window 个计数
+    #[arg(long)]
+    pub counter: Option<u64>,
+    #[arg(long, conflicts_with = "counter")]
+    pub time: Option<u64>,
+    // 允许的误差: TOTP 为前后的时间步数，HOTP 为向后查找的计数
+    // 过大的窗口既慢又会大幅降低安全性，因此限制在 100 以内
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(0..=MAX_OTP_WINDOW))]
+    pub window: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+fn parse_otp_algorithm(algorithm: &str) -> Result<OtpAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for OtpAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha1" => Ok(OtpAlgorithm::Sha1),
+            "sha256" => Ok(OtpAlgorithm::Sha256),
+            "sha512" => Ok(OtpAlgorithm::Sha512),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported algorithm. Supported algorithms: sha1, sha256, sha512"
+            )),
+        }
+    }
+}
+
+impl From<OtpAlgorithm> for &'static str {
+    fn from(value: OtpAlgorithm) -> Self {
+        match value {
+            OtpAlgorithm::Sha1 => "sha1",
+            OtpAlgorithm::Sha256 => "sha256",
+            OtpAlgorithm::Sha512 => "sha512",
+        }
+    }
+}
+
+impl fmt::Display for OtpAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt = Into::<&str>::into(*self);
+        write!(f, "{}", fmt)
+    }
+}
+
+fn now() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+impl CmdExector for OtpSecretOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        println!("{}", process_otp_secret(self.bytes as usize));
+        Ok(())
+    }
+}
+
+impl CmdExector for OtpUriOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let uri = process_otp_uri(
+            &self.otp.secret,
+            &self.account,
+            self.issuer.as_deref(),
+            self.counter,
+            &self.otp.params(),
+        )?;
+        if self.qr {
+            println!("{}", render_qr(&uri)?);
+        }
+        println!("{}", uri);
+        Ok(())
+    }
+}
+
+impl CmdExector for OtpCodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let params = self.otp.params();
+        if let Some(counter) = self.counter {
+            println!("{}", process_hotp(&self.otp.secret, counter, &params)?);
+            return Ok(());
+        }
+        let time = match self.time {
+            Some(time) => time,
+            None => now()?,
+        };
+        println!("{}", process_totp(&self.otp.secret, time, &params)?);
+        eprintln!("Valid for {}s", params.period - time % params.period);
+        Ok(())
+    }
+}
+
+impl CmdExector for OtpVerifyOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let params = self.otp.params();
+        if let Some(counter) = self.counter {
+            match process_hotp_verify(&self.otp.secret, &self.code, counter, self.window, &params)?
+            {
+                Some(matched) => eprintln!("Code is valid, next counter: {}", matched + 1),
+                None => anyhow::bail!("Invalid code"),
+            }
+            return Ok(());
+        }
+        let time = match self.time {
+            Some(time) => time,
+            None => now()?,
+        };
+        match process_totp_verify(&self.otp.secret, &self.code, time, self.window, &params)? {
+            Some(drift) => eprintln!("Code is valid, time step drift: {}", drift),
+            None => anyhow::bail!("Invalid code"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use clap::{ArgGroup, Args, Parser};
+use enum_dispatch::enum_dispatch;
+use std::path::PathBuf;
+
+use super::{parser_format, read_secret, PasswordRuleArgs};
+use crate::{process_vault_export, CmdExector, KdfParams, OutputFormat, Vault};
+
+/// 加密的本地密码库，整个库是一个 Argon2id + XChaCha20-Poly1305 加密的文件
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExector)]
+pub enum VaultSubCommand {
+    #[command(about = "Create a new vault protected by a master password")]
+    Init(VaultInitOpts),
+    #[command(about = "Add a credential, typed in or generated")]
+    Add(VaultAddOpts),
+    #[command(about = "Print the password of a credential")]
+    Get(VaultGetOpts),
+    #[command(about = "List credentials without passwords")]
+    List(VaultListOpts),
+    #[command(about = "Remove a credential")]
+    Rm(VaultRmOpts),
+    #[command(about = "Replace a password with a newly generated one")]
+    Rotate(VaultRotateOpts),
+    #[command(about = "Export all credentials in plaintext")]
+    Export(VaultExportOpts),
+}
+
+// 所有 vault 子命令共用的参数
+#[derive(Debug, Args)]
+pub struct VaultArgs {
+    // 密码库文件
+    #[arg(short, long, default_value = "rcli.vault")]
+    pub file: PathBuf,
+    // 从 stdin 逐行读取主密码 (以及 add 时的密码)，而不是在终端中输入，用于脚本
+    #[arg(long)]
+    pub master_stdin: bool,
+}
+
+impl VaultArgs {
+    fn open(&self) -> anyhow::Result<Vault> {
+        let master = read_secret("Master password: ", self.master_stdin)?;
+        Vault::open(&self.file, &master)
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct VaultInitOpts {
+    #[command(flatten)]
+    pub vault: VaultArgs,
+}
+
+impl CmdExector for VaultInitOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let master = read_secret("New master password: ", self.vault.master_stdin)?;
+        if !self.vault.master_stdin && read_secret("Repeat master password: ", false)? != master {
+            anyhow::bail!("Master passwords do not match");
+        }
+        Vault::init(&self.vault.file, &master, KdfParams::default())?;
+        eprintln!("Created vault {}", self.vault.file.display());
+        Ok(())
+    }
+}
+
+// 生成规则只在 --generate 时生效，单独指定时报错而不是被忽略
+#[derive(Debug, Parser)]
+#[command(group(
+    ArgGroup::new("rule_args")
+        .multiple(true)
+        .requires("generate")
+        .args([
+            "length", "uppercase", "lowercase", "numbers", "symbols",
+            "legacy_uppercase", "legacy_lowercase", "legacy_numbers", "legacy_symbols",
+            "policy", "policy_file", "rules", "symbols_set", "exclude", "include_ambiguous",
+            "min_upper", "min_lower", "min_digits", "min_symbols",
+        ])
+))]
+pub struct VaultAddOpts {
+    #[command(flatten)]
+    pub vault: VaultArgs,
+    #[arg(long)]
+    pub site: String,
+    #[arg(long)]
+    pub user: String,
+    // 使用 genpass 的规则生成密码并输出，不指定时输入密码
+    #[arg(long)]
+    pub generate: bool,
+    // --generate 时使用的规则
+    #[command(flatten)]
+    pub rule_args: PasswordRuleArgs,
+}
+
+impl CmdExector for VaultAddOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut vault = self.vault.open()?;
+        let password = if self.generate {
+            self.rule_args.generate()?
+        } else {
+            let prompt = format!("Password for {} @ {}: ", self.user, self.site);
+            read_secret(&prompt, self.vault.master_stdin)?
+        };
+        if password.is_empty() {
+            anyhow::bail!("Password must not be empty");
+        }
+        vault.add(&self.site, &self.user, password.clone())?;
+        vault.save()?;
+        // 生成的密码保存成功之后再输出
+        if self.generate {
+            println!("{}", password);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct VaultGetOpts {
+    #[command(flatten)]
+    pub vault: VaultArgs,
+    #[arg(long)]
+    pub site: String,
+    // 站点下只有一个用户时可以省略
+    #[arg(long)]
+    pub user: Option<String>,
+}
+
+impl CmdExector for VaultGetOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let vault = self.vault.open()?;
+        println!("{}", vault.get(&self.site, self.user.as_deref())?.password);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct VaultListOpts {
+    #[command(flatten)]
+    pub vault: VaultArgs,
+}
+
+impl CmdExector for VaultListOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let vault = self.vault.open()?;
+        println!("{:<30} {:<20} {:<17} ROTATED", "SITE", "USER", "CREATED");
+        for entry in &vault.entries {
+            let rotated = entry
+                .rotated
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "-".into());
+            println!(
+                "{:<30} {:<20} {:<17} {}",
+                entry.site,
+                entry.user,
+                entry.created.format("%Y-%m-%d %H:%M"),
+                rotated
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct VaultRmOpts {
+    #[command(flatten)]
+    pub vault: VaultArgs,
+    #[arg(long)]
+    pub site: String,
+    #[arg(long)]
+    pub user: Option<String>,
+}
+
+impl CmdExector for VaultRmOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut vault = self.vault.open()?;
+        let entry = vault.remove(&self.site, self.user.as_deref())?;
+        vault.save()?;
+        eprintln!("Removed {} @ {}", entry.user, entry.site);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct VaultRotateOpts {
+    #[command(flatten)]
+    pub vault: VaultArgs,
+    #[arg(long)]
+    pub site: String,
+    #[arg(long)]
+    pub user: Option<String>,
+    #[command(flatten)]
+    pub rule_args: PasswordRuleArgs,
+}
+
+impl CmdExector for VaultRotateOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut vault = self.vault.open()?;
+        let password = self.rule_args.generate()?;
+        vault.rotate(&self.site, self.user.as_deref(), password.clone())?;
+        vault.save()?;
+        println!("{}", password);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct VaultExportOpts {
+    #[command(flatten)]
+    pub vault: VaultArgs,
+    // 导出的文件，默认输出到 stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[arg(long, value_parser = parser_format, default_value = "json")]
+    pub format: OutputFormat,
+}
+
+impl CmdExector for VaultExportOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let vault = self.vault.open()?;
+        process_vault_export(&vault.entries, &self.output, self.format)?;
+        eprintln!(
+            "Exported {} credentials in plaintext, delete the export when you are done",
+            vault.entries.len()
+        );
+        Ok(())
+    }
+}
@@ -14,3 +14,9 @@ pub use utils::*;
 pub trait CmdExector {
     async fn execute(self) -> anyhow::Result<()>;
 }
+
+impl<T: CmdExector> CmdExector for Box<T> {
+    async fn execute(self) -> anyhow::Result<()> {
+        (*self).execute().await
+    }
+}
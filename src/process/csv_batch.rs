@@ -0,0 +1,200 @@
+use anyhow::Result;
+use rayon::prelude::*;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{csv_convert::process_csv, csv_date::DateNormalizer};
+use crate::cli::OutputFormat;
+
+/// 批量转换中单个文件的结果
+#[derive(Debug)]
+pub struct BatchResult {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub status: BatchStatus,
+}
+
+#[derive(Debug)]
+pub enum BatchStatus {
+    Ok,
+    Failed(anyhow::Error),
+    // 未开启 continue_on_error 时，出错之后剩下的文件不再处理
+    Skipped,
+}
+
+/// 判断输入是否需要走批量转换: 目录或者 glob
+pub fn is_batch_input(input: &str) -> bool {
+    Path::new(input).is_dir() || input.contains(['*', '?', '['])
+}
+
+/// 根据目录或 glob 找出所有的 csv 文件，并计算出对应的输出路径
+/// 输出目录中保持与输入相同的目录结构，扩展名换成输出格式
+pub fn collect_batch_inputs(
+    input: &str,
+    output_dir: &Path,
+    format: OutputFormat,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let (base, pattern) = if Path::new(input).is_dir() {
+        let base = PathBuf::from(input);
+        let pattern = base.join("**").join("*.csv");
+        (base, pattern.to_string_lossy().into_owned())
+    } else {
+        (glob_base(input), input.to_string())
+    };
+
+    // 统一成绝对路径再比较，避免 ./out 与 out 或者符号链接被当成不同的目录
+    // 输出目录可能还不存在，此时无法 canonicalize
+    let output_root = output_dir
+        .canonicalize()
+        .or_else(|_| std::path::absolute(output_dir))?;
+    let mut inputs = Vec::new();
+    for entry in glob::glob(&pattern)? {
+        let path = entry?;
+        // 输出目录在输入目录之下时，跳过上一次转换输出的文件
+        if !path.is_file() || path.canonicalize()?.starts_with(&output_root) {
+            continue;
+        }
+        let relative = path.strip_prefix(&base).unwrap_or(&path);
+        let output = output_dir
+            .join(relative)
+            .with_extension(Into::<&str>::into(format));
+        inputs.push((path, output));
+    }
+    if inputs.is_empty() {
+        anyhow::bail!("No csv files matched {}", input);
+    }
+    Ok(inputs)
+}
+
+/// glob 中第一个带通配符的部分之前的目录，例如 data/**/*.csv -> data
+pub(crate) fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+/// 在线程池中并行转换, jobs 为 0 时使用 CPU 核数
+pub fn process_csv_batch(
+    inputs: Vec<(PathBuf, PathBuf)>,
+    format: OutputFormat,
+    jobs: usize,
+    continue_on_error: bool,
+    dates: Option<&DateNormalizer>,
+) -> Result<Vec<BatchResult>> {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let failed = AtomicBool::new(false);
+    let results = pool.install(|| {
+        inputs
+            .into_par_iter()
+            .map(|(input, output)| {
+                if !continue_on_error && failed.load(Ordering::Relaxed) {
+                    return BatchResult {
+                        input,
+                        output,
+                        status: BatchStatus::Skipped,
+                    };
+                }
+                let status = match convert_one(&input, &output, format, dates) {
+                    Ok(()) => BatchStatus::Ok,
+                    Err(e) => {
+                        failed.store(true, Ordering::Relaxed);
+                        BatchStatus::Failed(e)
+                    }
+                };
+                BatchResult {
+                    input,
+                    output,
+                    status,
+                }
+            })
+            .collect()
+    });
+    Ok(results)
+}
+
+fn convert_one(
+    input: &Path,
+    output: &Path,
+    format: OutputFormat,
+    dates: Option<&DateNormalizer>,
+) -> Result<()> {
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let input = input
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Invalid path: {}", input.display()))?;
+    let output_str = output
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Invalid path: {}", output.display()))?;
+    // 输出是流式写入的，失败时删掉写了一半的文件
+    process_csv(input, output_str, format, dates).inspect_err(|_| {
+        let _ = fs::remove_file(output);
+    })
+}
+
+impl fmt::Display for BatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            BatchStatus::Ok => write!(
+                f,
+                "ok      {} -> {}",
+                self.input.display(),
+                self.output.display()
+            ),
+            BatchStatus::Failed(e) => write!(f, "failed  {}: {:#}", self.input.display(), e),
+            BatchStatus::Skipped => write!(f, "skipped {}", self.input.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_base() {
+        assert_eq!(glob_base("data/**/*.csv"), PathBuf::from("data"));
+        assert_eq!(glob_base("assets/*.csv"), PathBuf::from("assets"));
+        assert_eq!(glob_base("*.csv"), PathBuf::from(""));
+    }
+
+    #[test]
+    fn test_process_csv_batch() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let output_dir = tmp.path().join("out");
+        let inputs = collect_batch_inputs("assets", &output_dir, OutputFormat::Json)?;
+        assert_eq!(inputs.len(), 2);
+        assert!(inputs
+            .iter()
+            .any(|(_, o)| *o == output_dir.join("juventus.json")));
+
+        let results = process_csv_batch(inputs, OutputFormat::Json, 2, false, None)?;
+        assert!(results
+            .iter()
+            .all(|r| matches!(r.status, BatchStatus::Ok) && r.output.exists()));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_batch_inputs_skips_output_dir() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path().join("input");
+        fs::create_dir_all(dir.join("out"))?;
+        fs::copy("assets/juventus.csv", dir.join("a.csv"))?;
+        fs::copy("assets/juventus.csv", dir.join("out/a.csv"))?;
+        // 通过符号链接指定的输出目录也要被识别出来
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link)?;
+        let inputs =
+            collect_batch_inputs(dir.to_str().unwrap(), &link.join("out"), OutputFormat::Json)?;
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].0, dir.join("a.csv"));
+        Ok(())
+    }
+}
@@ -1,49 +1,141 @@
-use std::fs;
+use std::io::{BufWriter, Write};
 
 use anyhow::{Ok, Result};
 use csv::Reader;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 // crate 关键字确保你从包的根模块开始，避免路径解析上的混淆。
-use crate::cli::OutputFormat;
-
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "PascalCase")] // 使用标注，来制定整个struct的 rename
-struct Player {
-    // #[serde(rename = "Name")] //此时这个支持 PascalCase规则，这里无需添加
-    name: String,
-    // #[serde(rename = "Position")]
-    position: String,
-    #[serde(rename = "DOB")]
-    dob: String,
-    // #[serde(rename = "Nationality")]
-    nationality: String,
-    #[serde(rename = "Kit Number")]
-    kit: u8,
-}
+use super::csv_date::DateNormalizer;
+use crate::{cli::OutputFormat, utils::get_writer};
 
-pub fn process_csv(input: &str, output: &str, format: OutputFormat) -> Result<()> {
+pub fn process_csv(
+    input: &str,
+    output: &str,
+    format: OutputFormat,
+    dates: Option<&DateNormalizer>,
+) -> Result<()> {
     let mut reader = Reader::from_path(input)?;
-    let mut ret = Vec::with_capacity(128); // 长度通常使用常量来配置，这里暂时先用这种方式
     let headers = reader.headers()?.clone(); // 获取出头部
+    let mut writer = RecordWriter::new(output, format, headers.iter().map(String::from).collect())?;
     for result in reader.records() {
         //此处 reader.records方法，看上去是一个读取操作，实际上，reader内部，需要对当前读取位置的指针进行更新，因此，这里需要注意
         // 这里的result 实际上是一个Result， 使用? 实际上就是使用 anyhow 来处理这个异常
         let record = result?;
         // 使用Rust的 迭代iter.zip的方法，将两个迭代器合并成一个新的迭代器，如果两个迭代器中的长度不一致时，以短的为主
-        // 随后将结果通过collect方法，转换为seder_json 的value类型
-        let json_value = headers.iter().zip(record.iter()).collect::<Value>();
-        ret.push(json_value)
+        // 随后将结果通过collect方法，转换为seder_json 的 Map 类型
+        let mut json_value = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+            .collect::<Map<String, Value>>();
+        if let Some(dates) = dates {
+            dates.normalize(&mut json_value)?;
+        }
+        writer.write(&json_value)?;
     }
+    writer.finish()
+}
+
+/// 按 OutputFormat 逐条写出记录，不需要把整个文件读进内存
+/// json 输出与 serde_json::to_string_pretty(&Vec<_>) 的结果一致
+pub struct RecordWriter {
+    headers: Vec<String>,
+    sink: Sink,
+    count: usize,
+}
 
-    let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
-    };
+enum Sink {
+    Json(BufWriter<Box<dyn Write>>),
+    Yaml(BufWriter<Box<dyn Write>>),
+    Csv(Box<csv::Writer<Box<dyn Write>>>),
+}
+
+impl RecordWriter {
+    pub fn new(output: &str, format: OutputFormat, headers: Vec<String>) -> Result<Self> {
+        let writer = get_writer(output)?;
+        let sink = match format {
+            OutputFormat::Json => Sink::Json(BufWriter::new(writer)),
+            OutputFormat::Yaml => Sink::Yaml(BufWriter::new(writer)),
+            OutputFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(writer);
+                writer.write_record(&headers)?;
+                Sink::Csv(Box::new(writer))
+            }
+        };
+        Ok(Self {
+            headers,
+            sink,
+            count: 0,
+        })
+    }
 
-    //使用serde_json 生成一个JSON的字符串
-    // let json = serde_json::to_string_pretty(&ret)?;
-    fs::write(output, content)?; //此处返回的元组
-    Ok(()) // 显式的返回Result类型
+    pub fn write(&mut self, record: &Map<String, Value>) -> Result<()> {
+        match &mut self.sink {
+            Sink::Json(w) => {
+                w.write_all(if self.count == 0 { b"[\n" } else { b",\n" })?;
+                let content = serde_json::to_string_pretty(record)?;
+                for (i, line) in content.lines().enumerate() {
+                    if i > 0 {
+                        w.write_all(b"\n")?;
+                    }
+                    write!(w, "  {}", line)?;
+                }
+            }
+            // 单个元素的序列，拼接起来仍然是一个合法的 yaml 序列
+            Sink::Yaml(w) => w.write_all(serde_yaml::to_string(&[record])?.as_bytes())?,
+            Sink::Csv(w) => {
+                // csv 按照表头的顺序输出
+                let row = self.headers.iter().map(|h| match record.get(h) {
+                    Some(Value::String(s)) => s.clone(),
+                    Some(Value::Null) | None => String::new(),
+                    Some(v) => v.to_string(),
+                });
+                w.write_record(row)?;
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            Sink::Json(mut w) => {
+                w.write_all(if self.count == 0 { b"[]" } else { b"\n]" })?;
+                w.flush()?;
+            }
+            Sink::Yaml(mut w) => {
+                if self.count == 0 {
+                    w.write_all(b"[]\n")?;
+                }
+                w.flush()?;
+            }
+            Sink::Csv(mut w) => w.flush()?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_writer_json_matches_pretty() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("record_writer.json");
+        let output = output.to_str().unwrap();
+        process_csv("assets/juventus.csv", output, OutputFormat::Json, None)?;
+
+        let mut reader = Reader::from_path("assets/juventus.csv")?;
+        let headers = reader.headers()?.clone();
+        let expected = reader
+            .records()
+            .map(|r| Ok(headers.iter().zip(r?.iter()).collect::<Value>()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            std::fs::read_to_string(output)?,
+            serde_json::to_string_pretty(&expected)?
+        );
+        Ok(())
+    }
 }
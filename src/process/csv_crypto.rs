@@ -0,0 +1,164 @@
+use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, prelude::*};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use csv::{Reader, Writer};
+use std::{fs, path::Path};
+
+use super::text::KeyLoader;
+use crate::utils::{get_read, get_writer};
+
+/// XChaCha20-Poly1305 的 nonce 长度
+const NONCE_LEN: usize = 24;
+
+/// 列加密使用的 AEAD, key 文件格式与 blake3 的 key 相同 (32 字节)
+struct CsvCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl CsvCipher {
+    pub fn try_new(key: &[u8]) -> Result<Self> {
+        // 与 Blake3::try_new 一样，忽略 key 文件末尾的换行
+        let key = key
+            .get(..32)
+            .ok_or_else(|| anyhow::anyhow!("Key must be at least 32 bytes"))?;
+        let cipher = XChaCha20Poly1305::new_from_slice(key)?;
+        Ok(Self { cipher })
+    }
+
+    /// 每个单元格使用随机的 nonce, 列名作为附加数据，避免密文在列之间被挪用
+    /// 输出为 base64url(nonce || ciphertext)
+    fn encrypt(&self, column: &str, value: &str) -> Result<String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: value.as_bytes(),
+            aad: column.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt column `{}`", column))?;
+        let mut buf = nonce.to_vec();
+        buf.extend_from_slice(&ciphertext);
+        Ok(URL_SAFE_NO_PAD.encode(buf))
+    }
+
+    fn decrypt(&self, column: &str, value: &str) -> Result<String> {
+        let buf = URL_SAFE_NO_PAD.decode(value)?;
+        if buf.len() < NONCE_LEN {
+            anyhow::bail!("Invalid ciphertext in column `{}`", column);
+        }
+        let (nonce, ciphertext) = buf.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: column.as_bytes(),
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Failed to decrypt column `{}`: wrong key or tampered data",
+                    column
+                )
+            })?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+impl KeyLoader for CsvCipher {
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let key = fs::read(path)?;
+        Self::try_new(&key)
+    }
+}
+
+pub fn process_csv_encrypt(input: &str, output: &str, columns: &[String], key: &str) -> Result<()> {
+    let cipher = CsvCipher::load(key)?;
+    transform_columns(input, output, columns, |column, value| {
+        cipher.encrypt(column, value)
+    })
+}
+
+pub fn process_csv_decrypt(input: &str, output: &str, columns: &[String], key: &str) -> Result<()> {
+    let cipher = CsvCipher::load(key)?;
+    transform_columns(input, output, columns, |column, value| {
+        cipher.decrypt(column, value)
+    })
+}
+
+/// 逐行读取 csv，只对指定的列调用 f, 其余的列原样输出
+fn transform_columns<F>(input: &str, output: &str, columns: &[String], mut f: F) -> Result<()>
+where
+    F: FnMut(&str, &str) -> Result<String>,
+{
+    let mut reader = Reader::from_reader(get_read(input)?);
+    let headers = reader.headers()?.clone();
+    let mut targets = Vec::with_capacity(columns.len());
+    for name in columns {
+        let idx = headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| anyhow::anyhow!("Column `{}` not found in {}", name, input))?;
+        targets.push(idx);
+    }
+
+    let mut writer = Writer::from_writer(get_writer(output)?);
+    writer.write_record(&headers)?;
+    for result in reader.records() {
+        let record = result?;
+        let mut row: Vec<String> = record.iter().map(String::from).collect();
+        for idx in &targets {
+            row[*idx] = f(&headers[*idx], &row[*idx])?;
+        }
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_cipher_round_trip() -> Result<()> {
+        let cipher = CsvCipher::load("fixtures/blake3.txt")?;
+        let encrypted = cipher.encrypt("DOB", "Apr 18, 1990 (29)")?;
+        // 每次加密使用不同的 nonce
+        assert_ne!(encrypted, cipher.encrypt("DOB", "Apr 18, 1990 (29)")?);
+        assert_eq!(cipher.decrypt("DOB", &encrypted)?, "Apr 18, 1990 (29)");
+        // 换了列名之后无法解密
+        assert!(cipher.decrypt("Name", &encrypted).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_encrypt_decrypt() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        let encrypted = dir.join("encrypt.csv");
+        let decrypted = dir.join("decrypt.csv");
+        let columns = vec!["DOB".to_string(), "Kit Number".to_string()];
+        let key = "fixtures/blake3.txt";
+        process_csv_encrypt(
+            "assets/juventus.csv",
+            encrypted.to_str().unwrap(),
+            &columns,
+            key,
+        )?;
+        process_csv_decrypt(
+            encrypted.to_str().unwrap(),
+            decrypted.to_str().unwrap(),
+            &columns,
+            key,
+        )?;
+        assert_eq!(
+            fs::read_to_string("assets/juventus.csv")?,
+            fs::read_to_string(decrypted)?
+        );
+        Ok(())
+    }
+}
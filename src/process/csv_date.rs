@@ -0,0 +1,228 @@
+use anyhow::Result;
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use serde_json::{Map, Value};
+use std::fmt::{self, Write};
+
+use crate::cli::DateFormat;
+
+/// 未指定输入格式时，依次尝试的日期格式
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%b %d, %Y", "%d/%m/%Y", "%Y/%m/%d", "%B %d, %Y"];
+
+/// 未指定输入格式时，依次尝试的日期时间格式 (rfc3339/rfc2822 之外)
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%d/%m/%Y %H:%M:%S",
+    "%d/%m/%Y %H:%M",
+];
+
+/// 解析出来的值，只有日期的值不做时区转换
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParsedDate {
+    Date(NaiveDate),
+    Naive(NaiveDateTime),
+    Offset(DateTime<FixedOffset>),
+}
+
+/// 按 format 解析，format 为 None 时自动识别常见格式
+/// 值后面多余的内容会被忽略，例如 "Apr 18, 1990 (29)"
+pub fn parse_date(value: &str, format: Option<&str>) -> Option<ParsedDate> {
+    let value = value.trim();
+    match format {
+        Some(format) => DateTime::parse_and_remainder(value, format)
+            .map(|(dt, _)| ParsedDate::Offset(dt))
+            .or_else(|_| {
+                NaiveDateTime::parse_and_remainder(value, format)
+                    .map(|(dt, _)| ParsedDate::Naive(dt))
+            })
+            .or_else(|_| {
+                NaiveDate::parse_and_remainder(value, format).map(|(d, _)| ParsedDate::Date(d))
+            })
+            .ok(),
+        None => DateTime::parse_from_rfc3339(value)
+            .or_else(|_| DateTime::parse_from_rfc2822(value))
+            .map(ParsedDate::Offset)
+            .ok()
+            .or_else(|| {
+                DATETIME_FORMATS.iter().find_map(|f| {
+                    NaiveDateTime::parse_and_remainder(value, f)
+                        .ok()
+                        .map(|(dt, _)| ParsedDate::Naive(dt))
+                })
+            })
+            .or_else(|| {
+                DATE_FORMATS.iter().find_map(|f| {
+                    NaiveDate::parse_and_remainder(value, f)
+                        .ok()
+                        .map(|(d, _)| ParsedDate::Date(d))
+                })
+            }),
+    }
+}
+
+/// 校验 strftime 格式，chrono 遇到错误的格式时 to_string() 会 panic，需要在使用之前检查
+pub fn check_date_format(format: &str) -> Result<()> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        anyhow::bail!("Invalid strftime format `{}`", format);
+    }
+    Ok(())
+}
+
+/// 格式合法但与值不匹配时 (例如只有日期的值使用 %H) 同样会出错，这里返回错误而不是 panic
+pub(crate) fn format_date(formatted: impl fmt::Display, format: &str) -> Result<String> {
+    let mut output = String::new();
+    write!(output, "{}", formatted)
+        .map_err(|_| anyhow::anyhow!("Cannot format the value with `{}`", format))?;
+    Ok(output)
+}
+
+/// 将指定的列规范化成统一的日期格式
+#[derive(Debug, Clone)]
+pub struct DateNormalizer {
+    pub columns: Vec<String>,
+    pub input_format: Option<String>,
+    pub output_format: DateFormat,
+    /// 不带时区的时间按这个时区理解
+    pub from_tz: Option<Tz>,
+    /// 输出时转换到这个时区
+    pub to_tz: Option<Tz>,
+}
+
+impl DateNormalizer {
+    /// 空值保持为空，无法解析的值返回错误
+    pub fn normalize(&self, record: &mut Map<String, Value>) -> Result<()> {
+        for column in &self.columns {
+            let Some(Value::String(value)) = record.get(column) else {
+                continue;
+            };
+            if value.trim().is_empty() {
+                continue;
+            }
+            let parsed = parse_date(value, self.input_format.as_deref()).ok_or_else(|| {
+                anyhow::anyhow!("Cannot parse `{}` in column {} as a date", value, column)
+            })?;
+            let normalized = self.format(parsed)?;
+            record.insert(column.clone(), Value::String(normalized));
+        }
+        Ok(())
+    }
+
+    fn format(&self, parsed: ParsedDate) -> Result<String> {
+        let dt = match parsed {
+            ParsedDate::Date(date) => {
+                return Ok(match &self.output_format {
+                    DateFormat::Iso8601 => date.format("%Y-%m-%d").to_string(),
+                    DateFormat::Timestamp => date
+                        .and_hms_opt(0, 0, 0)
+                        .expect("midnight is always valid")
+                        .and_utc()
+                        .timestamp()
+                        .to_string(),
+                    DateFormat::Custom(format) => format_date(date.format(format), format)?,
+                })
+            }
+            // 没有时区信息，也不需要转换时，原样输出
+            ParsedDate::Naive(dt) if self.from_tz.is_none() && self.to_tz.is_none() => {
+                return Ok(match &self.output_format {
+                    DateFormat::Iso8601 => dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                    DateFormat::Timestamp => dt.and_utc().timestamp().to_string(),
+                    DateFormat::Custom(format) => format_date(dt.format(format), format)?,
+                });
+            }
+            ParsedDate::Naive(dt) => {
+                let tz = self.from_tz.unwrap_or(Tz::UTC);
+                // 夏令时切换时重复的时间取较早的一个
+                tz.from_local_datetime(&dt)
+                    .earliest()
+                    .ok_or_else(|| anyhow::anyhow!("{} does not exist in {}", dt, tz))?
+                    .fixed_offset()
+            }
+            ParsedDate::Offset(dt) => dt,
+        };
+        let dt = match self.to_tz {
+            Some(tz) => dt.with_timezone(&tz).fixed_offset(),
+            None => dt,
+        };
+        Ok(match &self.output_format {
+            DateFormat::Iso8601 if dt.offset().local_minus_utc() == 0 => dt
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            DateFormat::Iso8601 => dt.to_rfc3339_opts(SecondsFormat::AutoSi, false),
+            DateFormat::Timestamp => dt.timestamp().to_string(),
+            DateFormat::Custom(format) => format_date(dt.format(format), format)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date() {
+        let date = NaiveDate::from_ymd_opt(1990, 4, 18).unwrap();
+        assert_eq!(
+            parse_date("Apr 18, 1990 (29)", None),
+            Some(ParsedDate::Date(date))
+        );
+        assert_eq!(
+            parse_date("18/04/1990", Some("%d/%m/%Y")),
+            Some(ParsedDate::Date(date))
+        );
+        assert!(matches!(
+            parse_date("1990-04-18 08:30:00", None),
+            Some(ParsedDate::Naive(_))
+        ));
+        assert!(matches!(
+            parse_date("1990-04-18T08:30:00+02:00", None),
+            Some(ParsedDate::Offset(_))
+        ));
+        assert_eq!(parse_date("unknown", None), None);
+    }
+
+    #[test]
+    fn test_date_normalizer() -> Result<()> {
+        let normalizer = DateNormalizer {
+            columns: vec!["DOB".into(), "Kickoff".into()],
+            input_format: None,
+            output_format: DateFormat::Iso8601,
+            from_tz: Some(Tz::Europe__Rome),
+            to_tz: Some(Tz::UTC),
+        };
+        let mut record = Map::new();
+        record.insert("DOB".into(), "Apr 18, 1990 (29)".into());
+        record.insert("Kickoff".into(), "2019-07-21 20:45".into());
+        record.insert("Name".into(), "Wojciech Szczesny".into());
+        normalizer.normalize(&mut record)?;
+        assert_eq!(record["DOB"], "1990-04-18");
+        assert_eq!(record["Kickoff"], "2019-07-21T18:45:00Z");
+        assert_eq!(record["Name"], "Wojciech Szczesny");
+
+        record.insert("DOB".into(), "someday".into());
+        assert!(normalizer.normalize(&mut record).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_output_format() {
+        assert!("%Q".parse::<DateFormat>().is_err());
+        assert!("%d/%m/%Y".parse::<DateFormat>().is_ok());
+
+        // 只有日期的值不能使用时间的格式，返回错误而不是 panic
+        let normalizer = DateNormalizer {
+            columns: vec!["DOB".into()],
+            input_format: None,
+            output_format: DateFormat::Custom("%H:%M".into()),
+            from_tz: None,
+            to_tz: None,
+        };
+        let mut record = Map::new();
+        record.insert("DOB".into(), "Apr 18, 1990 (29)".into());
+        assert!(normalizer.normalize(&mut record).is_err());
+    }
+}
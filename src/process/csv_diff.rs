@@ -0,0 +1,197 @@
+use anyhow::Result;
+use colored::Colorize;
+use csv::Reader;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+/// 一行数据，以列名为 key，这样两个文件的列顺序不同也不影响比较
+type Row = BTreeMap<String, String>;
+/// 按 key 读取出来的 csv 内容: (表头, [(key, 行)])
+type KeyedRows = (Vec<String>, Vec<(String, Row)>);
+
+/// 两个 csv 文件按 key 列比较之后的结果
+#[derive(Debug, Default, Serialize)]
+pub struct CsvDiff {
+    pub key: String,
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+    pub added: Vec<Row>,
+    pub removed: Vec<Row>,
+    pub modified: Vec<RowChange>,
+}
+
+/// 同一个 key 下发生变化的行
+#[derive(Debug, Serialize)]
+pub struct RowChange {
+    pub key: String,
+    pub changes: Vec<CellChange>,
+}
+
+/// 单元格的变化
+#[derive(Debug, Serialize)]
+pub struct CellChange {
+    pub column: String,
+    pub old: String,
+    pub new: String,
+}
+
+pub fn process_csv_diff(old: &str, new: &str, key: &str) -> Result<CsvDiff> {
+    let (old_headers, old_rows) = read_keyed(old, key)?;
+    let (new_headers, new_rows) = read_keyed(new, key)?;
+
+    let mut diff = CsvDiff {
+        key: key.to_string(),
+        added_columns: new_headers
+            .iter()
+            .filter(|h| !old_headers.contains(h))
+            .cloned()
+            .collect(),
+        removed_columns: old_headers
+            .iter()
+            .filter(|h| !new_headers.contains(h))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+
+    // 先建立索引，再按照文件中原本的顺序输出，保证结果稳定
+    let old_index: HashMap<&str, &Row> = old_rows.iter().map(|(k, r)| (k.as_str(), r)).collect();
+    let new_index: HashMap<&str, &Row> = new_rows.iter().map(|(k, r)| (k.as_str(), r)).collect();
+
+    for (k, row) in &old_rows {
+        if !new_index.contains_key(k.as_str()) {
+            diff.removed.push(row.clone());
+        }
+    }
+
+    for (k, row) in &new_rows {
+        match old_index.get(k.as_str()) {
+            None => diff.added.push(row.clone()),
+            Some(old_row) => {
+                let changes = diff_row(old_row, row);
+                if !changes.is_empty() {
+                    diff.modified.push(RowChange {
+                        key: k.clone(),
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+    Ok(diff)
+}
+
+/// 读取 csv，返回表头和 (key, row) 列表，key 重复时直接报错
+fn read_keyed(path: &str, key: &str) -> Result<KeyedRows> {
+    let mut reader = Reader::from_path(path)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+    if !headers.iter().any(|h| h == key) {
+        anyhow::bail!("Key column `{}` not found in {}", key, path);
+    }
+
+    let mut seen = HashSet::new();
+    let mut rows = Vec::with_capacity(128);
+    for result in reader.records() {
+        let record = result?;
+        let row: Row = headers
+            .iter()
+            .cloned()
+            .zip(record.iter().map(String::from))
+            .collect();
+        let k = row.get(key).cloned().unwrap_or_default();
+        if !seen.insert(k.clone()) {
+            anyhow::bail!("Duplicate key `{}` in {}", k, path);
+        }
+        rows.push((k, row));
+    }
+    Ok((headers, rows))
+}
+
+/// 只比较两边都有的列，新增/删除的列已经在 added_columns/removed_columns 中体现
+fn diff_row(old: &Row, new: &Row) -> Vec<CellChange> {
+    old.iter()
+        .filter_map(|(column, o)| {
+            let n = new.get(column)?;
+            (o != n).then(|| CellChange {
+                column: column.clone(),
+                old: o.clone(),
+                new: n.clone(),
+            })
+        })
+        .collect()
+}
+
+fn fmt_row(row: &Row) -> String {
+    row.iter()
+        .map(|(k, v)| format!("{}={:?}", k, v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 终端中的展示形式, 是否带颜色由 colored 控制
+impl fmt::Display for CsvDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for column in &self.added_columns {
+            writeln!(f, "{}", format!("+ column {}", column).green())?;
+        }
+        for column in &self.removed_columns {
+            writeln!(f, "{}", format!("- column {}", column).red())?;
+        }
+        for row in &self.removed {
+            let k = row.get(&self.key).map(String::as_str).unwrap_or_default();
+            writeln!(f, "{}", format!("- {}: {}", k, fmt_row(row)).red())?;
+        }
+        for row in &self.added {
+            let k = row.get(&self.key).map(String::as_str).unwrap_or_default();
+            writeln!(f, "{}", format!("+ {}: {}", k, fmt_row(row)).green())?;
+        }
+        for change in &self.modified {
+            writeln!(f, "{}", format!("~ {}", change.key).yellow())?;
+            for cell in &change.changes {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    cell.column,
+                    format!("{:?}", cell.old).red(),
+                    format!("{:?}", cell.new).green(),
+                )?;
+            }
+        }
+        write!(
+            f,
+            "{} added, {} removed, {} modified",
+            self.added.len(),
+            self.removed.len(),
+            self.modified.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_diff() -> Result<()> {
+        let diff = process_csv_diff("assets/juventus.csv", "fixtures/juventus_new.csv", "Name")?;
+        // 列的顺序不同，但列相同
+        assert!(diff.added_columns.is_empty() && diff.removed_columns.is_empty());
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0]["Name"], "Carlo Pinsoglio");
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0]["Name"], "Dusan Vlahovic");
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].key, "Mattia Perin");
+        assert_eq!(diff.modified[0].changes[0].column, "Kit Number");
+        assert_eq!(diff.modified[0].changes[0].new, "22");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_diff_missing_key() {
+        assert!(process_csv_diff("assets/juventus.csv", "assets/juventus.csv", "Id").is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fs;
+
+use super::{
+    csv_convert::RecordWriter,
+    csv_date::{check_date_format, format_date},
+};
+use crate::cli::OutputFormat;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alessandro",
+    "Andrea",
+    "Carlo",
+    "Daniele",
+    "Federico",
+    "Gianluigi",
+    "Giorgio",
+    "Leonardo",
+    "Luca",
+    "Marco",
+    "Mattia",
+    "Paulo",
+    "Rodrigo",
+    "Sami",
+    "Wojciech",
+    "Emre",
+    "Blaise",
+    "Juan",
+    "Douglas",
+    "Mario",
+    "Moise",
+    "Cristiano",
+    "Miralem",
+    "Joao",
+    "Merih",
+    "Aaron",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Bonucci",
+    "Chiellini",
+    "Rugani",
+    "Spinazzola",
+    "Bernardeschi",
+    "Buffon",
+    "Pinsoglio",
+    "Szczesny",
+    "Perin",
+    "Dybala",
+    "Bentancur",
+    "Khedira",
+    "Emre",
+    "Matuidi",
+    "Cuadrado",
+    "Costa",
+    "Mandzukic",
+    "Kean",
+    "Ronaldo",
+    "Pjanic",
+    "Cancelo",
+    "Demiral",
+    "Ramsey",
+    "Rabiot",
+];
+
+/// 数据生成的 schema, 例如:
+///
+/// ```yaml
+/// columns:
+///   - name: Name
+///     type: name
+///   - name: DOB
+///     type: date
+///     from: 1980-01-01
+///     to: 2005-12-31
+///     format: "%b %d, %Y"
+///   - name: Kit Number
+///     type: number
+///     min: 1
+///     max: 99
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct FakeSchema {
+    pub columns: Vec<FakeColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FakeColumn {
+    pub name: String,
+    #[serde(flatten)]
+    pub generator: FakeGenerator,
+}
+
+/// 每一列的生成方式
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum FakeGenerator {
+    Name,
+    Date {
+        from: NaiveDate,
+        to: NaiveDate,
+        #[serde(default = "default_date_format")]
+        format: String,
+    },
+    Number {
+        min: i64,
+        max: i64,
+    },
+    Enum {
+        values: Vec<String>,
+    },
+    Uuid,
+    Regex {
+        pattern: String,
+    },
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+/// 编译之后的生成器, regex 只需要解析一次
+enum Generator {
+    Name,
+    Date {
+        from: NaiveDate,
+        days: i64,
+        format: String,
+    },
+    Number {
+        min: i64,
+        max: i64,
+    },
+    Enum(Vec<String>),
+    Uuid,
+    Regex(rand_regex::Regex),
+}
+
+impl TryFrom<FakeGenerator> for Generator {
+    type Error = anyhow::Error;
+
+    fn try_from(value: FakeGenerator) -> Result<Self> {
+        let generator = match value {
+            FakeGenerator::Name => Generator::Name,
+            FakeGenerator::Date { from, to, format } => {
+                let days = (to - from).num_days();
+                if days < 0 {
+                    anyhow::bail!("Date range is empty: {} > {}", from, to);
+                }
+                // 提前格式化一次，错误的格式 (或者 %H 等时间的格式) 在这里报错，生成时不会 panic
+                check_date_format(&format)?;
+                format_date(from.format(&format), &format)?;
+                Generator::Date { from, days, format }
+            }
+            FakeGenerator::Number { min, max } => {
+                if min > max {
+                    anyhow::bail!("Number range is empty: {} > {}", min, max);
+                }
+                Generator::Number { min, max }
+            }
+            FakeGenerator::Enum { values } => {
+                if values.is_empty() {
+                    anyhow::bail!("Enum values must not be empty");
+                }
+                Generator::Enum(values)
+            }
+            FakeGenerator::Uuid => Generator::Uuid,
+            FakeGenerator::Regex { pattern } => {
+                Generator::Regex(rand_regex::Regex::compile(&pattern, 8)?)
+            }
+        };
+        Ok(generator)
+    }
+}
+
+impl Generator {
+    fn generate(&self, rng: &mut StdRng) -> Value {
+        match self {
+            Generator::Name => {
+                let first = FIRST_NAMES.choose(rng).expect("FIRST_NAMES won't be empty");
+                let last = LAST_NAMES.choose(rng).expect("LAST_NAMES won't be empty");
+                Value::String(format!("{} {}", first, last))
+            }
+            Generator::Date { from, days, format } => {
+                let date = *from + Duration::days(rng.gen_range(0..=*days));
+                Value::String(date.format(format).to_string())
+            }
+            Generator::Number { min, max } => Value::from(rng.gen_range(*min..=*max)),
+            Generator::Enum(values) => {
+                Value::String(values.choose(rng).expect("values won't be empty").clone())
+            }
+            Generator::Uuid => {
+                let uuid = uuid::Builder::from_random_bytes(rng.gen()).into_uuid();
+                Value::String(uuid.to_string())
+            }
+            Generator::Regex(regex) => Value::String(rng.sample(regex)),
+        }
+    }
+}
+
+pub fn process_csv_fake(
+    schema: &str,
+    output: &str,
+    format: OutputFormat,
+    rows: usize,
+    seed: Option<u64>,
+) -> Result<()> {
+    let schema: FakeSchema = serde_yaml::from_str(&fs::read_to_string(schema)?)?;
+    let headers: Vec<String> = schema.columns.iter().map(|c| c.name.clone()).collect();
+    let generators = schema
+        .columns
+        .into_iter()
+        .map(|c| Generator::try_from(c.generator))
+        .collect::<Result<Vec<_>>>()?;
+
+    // 指定 seed 时，同样的 schema 每次生成同样的数据
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let mut writer = RecordWriter::new(output, format, headers.clone())?;
+    for _ in 0..rows {
+        let record = headers
+            .iter()
+            .zip(generators.iter())
+            .map(|(name, generator)| (name.clone(), generator.generate(&mut rng)))
+            .collect::<Map<String, Value>>();
+        writer.write(&record)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_fake_is_reproducible() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        let a = dir.join("fake_a.csv");
+        let b = dir.join("fake_b.csv");
+        for output in [&a, &b] {
+            process_csv_fake(
+                "fixtures/fake_schema.yaml",
+                output.to_str().unwrap(),
+                OutputFormat::Csv,
+                50,
+                Some(42),
+            )?;
+        }
+        let content = fs::read_to_string(&a)?;
+        assert_eq!(content, fs::read_to_string(&b)?);
+        assert_eq!(content.lines().count(), 51);
+        assert!(content.starts_with("Name,Position,DOB,Nationality,Kit Number,Id,Code\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_date_format() {
+        for format in ["%Q", "%H:%M"] {
+            let generator = FakeGenerator::Date {
+                from: NaiveDate::from_ymd_opt(1990, 1, 1).unwrap(),
+                to: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+                format: format.into(),
+            };
+            assert!(Generator::try_from(generator).is_err());
+        }
+    }
+}
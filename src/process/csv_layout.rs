@@ -0,0 +1,185 @@
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::io::{BufRead, BufReader};
+
+use super::{csv_convert::RecordWriter, csv_date::DateNormalizer};
+use crate::{cli::OutputFormat, utils::get_read};
+
+/// 非 csv 的文本输入，每一行解析成一条记录
+#[derive(Debug, Clone)]
+pub enum TextLayout {
+    /// 定长格式，按字符位置切分
+    FixedWidth(Vec<FixedColumn>),
+    /// 使用正则的命名捕获组，组名即列名 (Regex 较大，放在 Box 中避免 CsvOpts 过大)
+    Regex(Box<Regex>),
+}
+
+/// 定长格式中的一列，范围为 [start, end)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedColumn {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl TextLayout {
+    /// 解析 "Name:0-20,Position:20-32" 形式的列定义
+    pub fn fixed_width(spec: &str) -> Result<Self> {
+        let columns = spec
+            .split(',')
+            .map(|column| {
+                let (name, range) = column.rsplit_once(':').ok_or_else(|| {
+                    anyhow::anyhow!("Invalid column `{}`, expect Name:0-20", column)
+                })?;
+                let (start, end) = range
+                    .split_once('-')
+                    .ok_or_else(|| anyhow::anyhow!("Invalid range `{}`, expect 0-20", range))?;
+                let (start, end) = (start.trim().parse()?, end.trim().parse()?);
+                if start >= end {
+                    anyhow::bail!("Column `{}` has an empty range {}-{}", name, start, end);
+                }
+                Ok(FixedColumn {
+                    name: name.trim().to_string(),
+                    start,
+                    end,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(TextLayout::FixedWidth(columns))
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)?;
+        if regex.capture_names().flatten().next().is_none() {
+            anyhow::bail!("Regex must contain named capture groups, e.g. (?<Name>\\w+)");
+        }
+        Ok(TextLayout::Regex(Box::new(regex)))
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        match self {
+            TextLayout::FixedWidth(columns) => columns.iter().map(|c| c.name.clone()).collect(),
+            TextLayout::Regex(regex) => regex.capture_names().flatten().map(String::from).collect(),
+        }
+    }
+
+    /// 将一行解析成一条记录，正则不匹配时返回 None
+    fn parse_line(&self, line: &str) -> Option<Map<String, Value>> {
+        match self {
+            TextLayout::FixedWidth(columns) => Some(
+                columns
+                    .iter()
+                    .map(|c| {
+                        // 按字符切分，行比定义短时缺少的列为空
+                        let value: String =
+                            line.chars().skip(c.start).take(c.end - c.start).collect();
+                        (c.name.clone(), Value::String(value.trim().to_string()))
+                    })
+                    .collect(),
+            ),
+            TextLayout::Regex(regex) => {
+                let caps = regex.captures(line)?;
+                Some(
+                    regex
+                        .capture_names()
+                        .flatten()
+                        .map(|name| {
+                            let value = caps.name(name).map_or("", |m| m.as_str());
+                            (name.to_string(), Value::String(value.to_string()))
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+/// 按 layout 解析文本，输出与 process_csv 相同的格式，空行会被忽略
+pub fn process_text_layout(
+    input: &str,
+    output: &str,
+    format: OutputFormat,
+    layout: &TextLayout,
+    dates: Option<&DateNormalizer>,
+) -> Result<()> {
+    let reader = BufReader::new(get_read(input)?);
+    let mut writer = RecordWriter::new(output, format, layout.headers())?;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut record = layout.parse_line(line).ok_or_else(|| {
+            anyhow::anyhow!("Line {} does not match the regex: {}", index + 1, line)
+        })?;
+        if let Some(dates) = dates {
+            dates.normalize(&mut record)?;
+        }
+        writer.write(&record)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_fixed_width_spec() -> Result<()> {
+        let TextLayout::FixedWidth(columns) =
+            TextLayout::fixed_width("Name:0-20,Kit Number:56-59")?
+        else {
+            panic!("expect fixed width layout");
+        };
+        assert_eq!(
+            columns[1],
+            FixedColumn {
+                name: "Kit Number".into(),
+                start: 56,
+                end: 59
+            }
+        );
+        assert!(TextLayout::fixed_width("Name:20-0").is_err());
+        assert!(TextLayout::fixed_width("Name").is_err());
+        assert!(TextLayout::regex(r"(\w+) (\w+)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_layout() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        let layout = TextLayout::fixed_width(
+            "Name:0-20,Position:20-32,DOB:32-46,Nationality:46-56,Kit Number:56-59",
+        )?;
+        let output = dir.join("fixed_width.csv");
+        process_text_layout(
+            "fixtures/juventus_fixed.txt",
+            output.to_str().unwrap(),
+            OutputFormat::Csv,
+            &layout,
+            None,
+        )?;
+        let content = fs::read_to_string(&output)?;
+        assert_eq!(content.lines().count(), 4);
+        assert!(content.contains("Wojciech Szczesny,Goalkeeper,\"Apr 18, 1990\",Poland,1\n"));
+
+        let layout = TextLayout::regex(r"^(?<Name>.+?)\s{2,}(?<Position>\S+)")?;
+        let output = dir.join("regex_layout.json");
+        process_text_layout(
+            "fixtures/juventus_fixed.txt",
+            output.to_str().unwrap(),
+            OutputFormat::Json,
+            &layout,
+            None,
+        )?;
+        let records: Vec<Map<String, Value>> = serde_json::from_str(&fs::read_to_string(&output)?)?;
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2]["Name"], "Leonardo Bonucci");
+        assert_eq!(records[2]["Position"], "Defender");
+        Ok(())
+    }
+}
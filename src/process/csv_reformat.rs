@@ -0,0 +1,178 @@
+use anyhow::Result;
+use csv::{ByteRecord, QuoteStyle, ReaderBuilder, Terminator, WriterBuilder};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, Read, Write},
+};
+
+use crate::{
+    cli::{CsvLineTerminator, CsvQuoteStyle},
+    utils::{get_read, get_writer},
+};
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// 自动识别分隔符时的候选
+const DELIMITERS: &[u8] = b",;\t|";
+
+/// 输出的 csv 方言以及清理规则
+#[derive(Debug)]
+pub struct ReformatOptions {
+    /// 输入的分隔符，None 时根据表头自动识别
+    pub input_delimiter: Option<u8>,
+    pub delimiter: u8,
+    pub quote: CsvQuoteStyle,
+    pub terminator: CsvLineTerminator,
+    pub bom: bool,
+    pub trim: bool,
+    pub drop_empty: bool,
+    pub dedupe_headers: bool,
+}
+
+/// 将任意方言的 csv 重写成统一的格式，同样的数据总是得到字节完全相同的输出
+pub fn process_csv_reformat(input: &str, output: &str, opts: &ReformatOptions) -> Result<()> {
+    let mut reader = BufReader::new(get_read(input)?);
+    // 去掉输入中的 BOM，输出时是否带 BOM 由参数决定
+    if reader.fill_buf()?.starts_with(BOM) {
+        reader.consume(BOM.len());
+    }
+    let delimiter = match opts.input_delimiter {
+        Some(d) => d,
+        None => sniff_delimiter(reader.fill_buf()?),
+    };
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(Box::new(reader) as Box<dyn Read>);
+    let mut out = get_writer(output)?;
+    if opts.bom {
+        out.write_all(BOM)?;
+    }
+    let mut writer = WriterBuilder::new()
+        .delimiter(opts.delimiter)
+        .quote_style(opts.quote.into())
+        .terminator(opts.terminator.into())
+        .from_writer(out);
+
+    let mut headers = reader.byte_headers()?.clone();
+    if opts.trim {
+        headers.trim();
+    }
+    if opts.dedupe_headers {
+        headers = dedupe(&headers);
+    }
+    writer.write_byte_record(&headers)?;
+
+    let mut record = ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        if opts.trim {
+            record.trim();
+        }
+        if opts.drop_empty && record.iter().all(|field| field.is_empty()) {
+            continue;
+        }
+        writer.write_byte_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 根据第一行中各个候选分隔符出现的次数，选出最多的那个
+fn sniff_delimiter(buf: &[u8]) -> u8 {
+    let line = buf.split(|b| *b == b'\n').next().unwrap_or_default();
+    DELIMITERS
+        .iter()
+        .copied()
+        .max_by_key(|d| line.iter().filter(|b| *b == d).count())
+        .filter(|d| line.contains(d))
+        .unwrap_or(b',')
+}
+
+/// 重复的列名加上序号, 例如 Name, Name -> Name, Name_2
+/// 原有的列名都先占用，已经存在 Name_2 时继续递增, 得到 Name_3
+fn dedupe(headers: &ByteRecord) -> ByteRecord {
+    let mut used: HashSet<Vec<u8>> = headers.iter().map(<[u8]>::to_vec).collect();
+    let mut seen: HashSet<&[u8]> = HashSet::new();
+    let mut suffixes: HashMap<&[u8], usize> = HashMap::new();
+    headers
+        .iter()
+        .map(|h| {
+            if seen.insert(h) {
+                return h.to_vec();
+            }
+            let suffix = suffixes.entry(h).or_insert(1);
+            loop {
+                *suffix += 1;
+                let mut name = h.to_vec();
+                name.extend_from_slice(format!("_{}", suffix).as_bytes());
+                if used.insert(name.clone()) {
+                    return name;
+                }
+            }
+        })
+        .collect()
+}
+
+impl From<CsvQuoteStyle> for QuoteStyle {
+    fn from(value: CsvQuoteStyle) -> Self {
+        match value {
+            CsvQuoteStyle::Minimal => QuoteStyle::Necessary,
+            CsvQuoteStyle::All => QuoteStyle::Always,
+            CsvQuoteStyle::NonNumeric => QuoteStyle::NonNumeric,
+        }
+    }
+}
+
+impl From<CsvLineTerminator> for Terminator {
+    fn from(value: CsvLineTerminator) -> Self {
+        match value {
+            CsvLineTerminator::Lf => Terminator::Any(b'\n'),
+            CsvLineTerminator::Crlf => Terminator::CRLF,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_sniff_delimiter() {
+        assert_eq!(sniff_delimiter(b"a;b;c\n1;2;3"), b';');
+        assert_eq!(sniff_delimiter(b"a\tb\n"), b'\t');
+        assert_eq!(sniff_delimiter(b"a,b"), b',');
+        assert_eq!(sniff_delimiter(b"single"), b',');
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let headers = ByteRecord::from(vec!["Name", "Name", "Name_2", "Name"]);
+        assert_eq!(
+            dedupe(&headers),
+            ByteRecord::from(vec!["Name", "Name_3", "Name_2", "Name_4"])
+        );
+    }
+
+    #[test]
+    fn test_process_csv_reformat() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("reformat.csv");
+        let opts = ReformatOptions {
+            input_delimiter: None,
+            delimiter: b',',
+            quote: CsvQuoteStyle::Minimal,
+            terminator: CsvLineTerminator::Lf,
+            bom: false,
+            trim: true,
+            drop_empty: true,
+            dedupe_headers: true,
+        };
+        process_csv_reformat("fixtures/dialect.csv", output.to_str().unwrap(), &opts)?;
+        assert_eq!(
+            fs::read_to_string(output)?,
+            "Name,Position,Name_2\nWojciech Szczesny,Goalkeeper,Szczesny; W\nMattia Perin,Goalkeeper,Perin\n"
+        );
+        Ok(())
+    }
+}
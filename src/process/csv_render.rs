@@ -0,0 +1,206 @@
+use anyhow::Result;
+use csv::Reader;
+use minijinja::{context, Environment, Error, ErrorKind};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use super::csv_date::{check_date_format, format_date, parse_date, ParsedDate};
+use crate::utils::get_writer;
+
+type Row = BTreeMap<String, String>;
+
+/// 使用 jinja 风格的模板渲染 csv
+/// - 指定 per_row 时，每一行渲染一次，输出到 output_dir 下，文件名由 filename 模板决定
+/// - 否则整个数据集渲染一次，模板中可以使用 headers 和 rows
+pub fn process_csv_render(
+    input: &str,
+    template: &str,
+    output: &str,
+    per_row: Option<(&Path, &str)>,
+) -> Result<()> {
+    let mut env = Environment::new();
+    register_filters(&mut env);
+    // 使用模板的文件名，这样 .html/.xml 模板会自动开启转义
+    let name = Path::new(template)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("template")
+        .to_string();
+    env.set_keep_trailing_newline(true);
+    env.add_template_owned(name.clone(), fs::read_to_string(template)?)?;
+    if let Some((_, filename)) = per_row {
+        env.add_template_owned("filename", filename.to_string())?;
+    }
+
+    let mut reader = Reader::from_path(input)?;
+    let headers: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+    let rows = reader
+        .records()
+        .map(|r| {
+            let record = r?;
+            Ok(headers
+                .iter()
+                .cloned()
+                .zip(record.iter().map(String::from))
+                .collect::<Row>())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let tmpl = env.get_template(&name)?;
+    match per_row {
+        None => {
+            let content = tmpl.render(context! { headers => headers, rows => rows })?;
+            get_writer(output)?.write_all(content.as_bytes())?;
+        }
+        Some((output_dir, _)) => {
+            let filename_tmpl = env.get_template("filename")?;
+            fs::create_dir_all(output_dir)?;
+            // 渲染出来的文件名 -> 行号，避免两行写到同一个文件，后一行静默地覆盖前一行
+            let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+            for (index, row) in rows.iter().enumerate() {
+                // 列可以直接作为变量使用，列名中有空格时使用 row["Kit Number"]
+                let ctx = context! { row => row, index => index, ..minijinja::Value::from_serialize(row) };
+                let filename = PathBuf::from(filename_tmpl.render(&ctx)?.trim());
+                check_filename(&filename, index)?;
+                if let Some(prev) = seen.insert(filename.clone(), index) {
+                    anyhow::bail!(
+                        "Rows {} and {} both render to {}, make the filename template unique",
+                        prev,
+                        index,
+                        filename.display()
+                    );
+                }
+                // 文件名可以包含子目录，例如 "{{ Team }}/{{ Name }}.md"
+                let path = output_dir.join(filename);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, tmpl.render(&ctx)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 文件名来自 csv 的数据，不能是绝对路径，也不能通过 .. 写到 output_dir 之外
+fn check_filename(filename: &Path, index: usize) -> Result<()> {
+    let inside = filename
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if filename.as_os_str().is_empty() || !inside {
+        anyhow::bail!(
+            "Row {} renders an invalid filename `{}`, it must be a relative path inside the output directory",
+            index,
+            filename.display()
+        );
+    }
+    Ok(())
+}
+
+fn register_filters(env: &mut Environment) {
+    env.add_filter("date", date_filter);
+    env.add_filter("slug", slug_filter);
+}
+
+/// `{{ DOB | date("%Y-%m-%d") }}`, 第二个参数可以指定输入的格式，不指定时自动识别常见格式
+fn date_filter(
+    value: String,
+    format: Option<String>,
+    input: Option<String>,
+) -> Result<String, Error> {
+    let date = match parse_date(&value, input.as_deref()) {
+        Some(ParsedDate::Date(date)) => date,
+        Some(ParsedDate::Naive(dt)) => dt.date(),
+        Some(ParsedDate::Offset(dt)) => dt.date_naive(),
+        None => {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("cannot parse `{}` as a date", value.trim()),
+            ))
+        }
+    };
+    let format = format.as_deref().unwrap_or("%Y-%m-%d");
+    check_date_format(format)
+        .and_then(|_| format_date(date.format(format), format))
+        .map_err(|e| Error::new(ErrorKind::InvalidOperation, e.to_string()))
+}
+
+/// 转换成适合做文件名的形式, 例如 "Wojciech Szczesny" -> "wojciech-szczesny"
+fn slug_filter(value: String) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_filter() {
+        assert_eq!(
+            date_filter("Apr 18, 1990 (29)".into(), None, None).unwrap(),
+            "1990-04-18"
+        );
+        assert_eq!(
+            date_filter(
+                "18/04/1990".into(),
+                Some("%Y".into()),
+                Some("%d/%m/%Y".into())
+            )
+            .unwrap(),
+            "1990"
+        );
+        assert!(date_filter("unknown".into(), None, None).is_err());
+        assert!(date_filter("1990-04-18".into(), Some("%Q".into()), None).is_err());
+    }
+
+    #[test]
+    fn test_process_csv_render_per_row() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path().join("players");
+        process_csv_render(
+            "assets/juventus.csv",
+            "fixtures/player.md.j2",
+            "-",
+            Some((&dir, "{{ Name | slug }}.md")),
+        )?;
+        let content = fs::read_to_string(dir.join("wojciech-szczesny.md"))?;
+        assert!(content.starts_with("# WOJCIECH SZCZESNY\n"));
+        assert!(content.contains("- Born: 1990-04-18"));
+        assert!(content.contains("- Kit: 1"));
+
+        // 文件名中的子目录会自动创建
+        process_csv_render(
+            "assets/juventus.csv",
+            "fixtures/player.md.j2",
+            "-",
+            Some((&dir, "{{ Position | slug }}/{{ Name | slug }}.md")),
+        )?;
+        assert!(dir.join("goalkeeper/wojciech-szczesny.md").is_file());
+
+        // 文件名不能逃出输出目录，也不能重复
+        for filename in [
+            "../{{ Name | slug }}.md",
+            "/tmp/{{ index }}.md",
+            "player.md",
+        ] {
+            let per_row = Some((dir.as_path(), filename));
+            assert!(process_csv_render(
+                "assets/juventus.csv",
+                "fixtures/player.md.j2",
+                "-",
+                per_row
+            )
+            .is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::Result;
+use csv::{ByteRecord, Position, Reader, Writer};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    cli::RowRange,
+    utils::{get_read, get_writer},
+};
+
+/// 索引文件的头部: magic + 输入文件大小 + 输入文件修改时间，之后每行一个 u64 的字节偏移
+const INDEX_MAGIC: &[u8; 8] = b"RCLIIDX1";
+const INDEX_HEADER_LEN: u64 = 24;
+
+/// 蓄水池抽样，只需要遍历一遍文件，内存中最多保留 count 行
+/// 指定 by 时按该列的取值分层，每个取值各抽 count 行
+/// 输出保持原文件中的顺序，返回抽出的行数
+pub fn process_csv_sample(
+    input: &str,
+    output: &str,
+    count: usize,
+    by: Option<&str>,
+    seed: Option<u64>,
+) -> Result<usize> {
+    let mut reader = Reader::from_reader(get_read(input)?);
+    let headers = reader.byte_headers()?.clone();
+    let by = by
+        .map(|column| {
+            headers
+                .iter()
+                .position(|h| h == column.as_bytes())
+                .ok_or_else(|| anyhow::anyhow!("Column `{}` not found", column))
+        })
+        .transpose()?;
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // 不分层时所有行都落在同一个 key 下
+    let mut reservoirs: HashMap<Vec<u8>, Reservoir> = HashMap::new();
+    let mut record = ByteRecord::new();
+    let mut index = 0;
+    while reader.read_byte_record(&mut record)? {
+        let key = by.and_then(|i| record.get(i)).unwrap_or_default().to_vec();
+        reservoirs
+            .entry(key)
+            .or_default()
+            .offer(index, &record, count, &mut rng);
+        index += 1;
+    }
+
+    let mut rows: Vec<_> = reservoirs.into_values().flat_map(|r| r.rows).collect();
+    rows.sort_by_key(|(index, _)| *index);
+    let mut writer = Writer::from_writer(get_writer(output)?);
+    writer.write_byte_record(&headers)?;
+    for (_, row) in &rows {
+        writer.write_byte_record(row)?;
+    }
+    writer.flush()?;
+    Ok(rows.len())
+}
+
+#[derive(Default)]
+struct Reservoir {
+    seen: usize,
+    rows: Vec<(usize, ByteRecord)>,
+}
+
+impl Reservoir {
+    /// Algorithm R: 第 k 行以 count / k 的概率替换掉池中随机的一行
+    fn offer(&mut self, index: usize, record: &ByteRecord, count: usize, rng: &mut StdRng) {
+        if self.rows.len() < count {
+            self.rows.push((index, record.clone()));
+        } else {
+            let j = rng.gen_range(0..=self.seen);
+            if j < count {
+                self.rows[j] = (index, record.clone());
+            }
+        }
+        self.seen += 1;
+    }
+}
+
+/// 输出 rows 范围内的行 (不含表头，从 0 开始)
+/// use_index 时借助 <input>.idx 直接 seek 到起始行，索引不存在或者过期时会自动重建
+pub fn process_csv_slice(input: &str, output: &str, rows: RowRange, use_index: bool) -> Result<()> {
+    if use_index && input == "-" {
+        anyhow::bail!("--index requires a file input, stdin can't be indexed or seeked");
+    }
+    let mut writer = Writer::from_writer(get_writer(output)?);
+    let count = rows.end.map(|end| end.saturating_sub(rows.start));
+    if use_index {
+        let mut reader = Reader::from_path(input)?;
+        writer.write_byte_record(reader.byte_headers()?)?;
+        // 起始行超出文件的行数时只输出表头
+        if let Some(offset) = index_offset(input, rows.start)? {
+            let mut position = Position::new();
+            position.set_byte(offset);
+            reader.seek(position)?;
+            copy_records(&mut reader, &mut writer, count)?;
+        }
+    } else {
+        let mut reader = Reader::from_reader(get_read(input)?);
+        writer.write_byte_record(reader.byte_headers()?)?;
+        let mut record = ByteRecord::new();
+        for _ in 0..rows.start {
+            if !reader.read_byte_record(&mut record)? {
+                break;
+            }
+        }
+        copy_records(&mut reader, &mut writer, count)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 复制 count 行，None 代表复制到文件末尾
+fn copy_records<R: Read, W: Write>(
+    reader: &mut Reader<R>,
+    writer: &mut Writer<W>,
+    count: Option<usize>,
+) -> Result<()> {
+    let mut record = ByteRecord::new();
+    let mut remaining = count;
+    while remaining != Some(0) && reader.read_byte_record(&mut record)? {
+        writer.write_byte_record(&record)?;
+        remaining = remaining.map(|r| r - 1);
+    }
+    Ok(())
+}
+
+/// 索引文件与输入放在一起，例如 data.csv -> data.csv.idx
+fn index_path(input: &str) -> String {
+    format!("{}.idx", input)
+}
+
+/// 为每一行记录它在文件中的字节偏移，之后可以直接 seek 到任意一行
+fn build_csv_index(input: &str) -> Result<u64> {
+    let mut reader = Reader::from_path(input)?;
+    let mut writer = BufWriter::new(File::create(index_path(input))?);
+    writer.write_all(&index_header(input)?)?;
+    let mut record = ByteRecord::new();
+    let mut count = 0;
+    while reader.read_byte_record(&mut record)? {
+        let offset = record.position().map(Position::byte).unwrap_or_default();
+        writer.write_all(&offset.to_le_bytes())?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// 从索引中读出第 row 行的字节偏移，超出行数时返回 None
+fn index_offset(input: &str, row: usize) -> Result<Option<u64>> {
+    let path = index_path(input);
+    let expected = index_header(input)?;
+    let fresh = match File::open(&path) {
+        Ok(mut file) => {
+            let mut header = [0u8; INDEX_HEADER_LEN as usize];
+            file.read_exact(&mut header).is_ok() && expected == header
+        }
+        Err(_) => false,
+    };
+    if !fresh {
+        build_csv_index(input)?;
+    }
+
+    let mut file = File::open(&path)?;
+    let rows = file
+        .metadata()?
+        .len()
+        .checked_sub(INDEX_HEADER_LEN)
+        .ok_or_else(|| anyhow::anyhow!("Index file {} is truncated, delete it and retry", path))?
+        / 8;
+    if row as u64 >= rows {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::Start(INDEX_HEADER_LEN + row as u64 * 8))?;
+    let mut offset = [0u8; 8];
+    file.read_exact(&mut offset)?;
+    Ok(Some(u64::from_le_bytes(offset)))
+}
+
+/// 记录输入文件的大小和修改时间，任意一个变化都说明索引已经过期
+fn index_header(input: &str) -> Result<Vec<u8>> {
+    let metadata = fs::metadata(input)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+    let mut header = INDEX_MAGIC.to_vec();
+    header.extend_from_slice(&metadata.len().to_le_bytes());
+    header.extend_from_slice(&modified.to_le_bytes());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_sample() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        let a = dir.join("sample_a.csv");
+        let b = dir.join("sample_b.csv");
+        for output in [&a, &b] {
+            let count = process_csv_sample(
+                "assets/juventus.csv",
+                output.to_str().unwrap(),
+                5,
+                None,
+                Some(7),
+            )?;
+            assert_eq!(count, 5);
+        }
+        assert_eq!(fs::read_to_string(&a)?, fs::read_to_string(&b)?);
+
+        // 按位置分层，每个位置最多 2 行
+        let count = process_csv_sample(
+            "assets/juventus.csv",
+            a.to_str().unwrap(),
+            2,
+            Some("Position"),
+            Some(7),
+        )?;
+        let mut reader = Reader::from_path(&a)?;
+        let mut per_position: HashMap<String, usize> = HashMap::new();
+        for record in reader.records() {
+            *per_position.entry(record?[1].to_string()).or_default() += 1;
+        }
+        assert_eq!(per_position.values().sum::<usize>(), count);
+        assert!(per_position.values().all(|n| *n <= 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_slice_with_index() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        let input = dir.join("slice_input.csv");
+        fs::copy("assets/juventus.csv", &input)?;
+        let input = input.to_str().unwrap();
+        let plain = dir.join("slice_plain.csv");
+        let indexed = dir.join("slice_indexed.csv");
+        for range in ["3..6", "20..", "100..200"] {
+            let rows: RowRange = range.parse()?;
+            process_csv_slice(input, plain.to_str().unwrap(), rows, false)?;
+            process_csv_slice(input, indexed.to_str().unwrap(), rows, true)?;
+            assert_eq!(fs::read_to_string(&plain)?, fs::read_to_string(&indexed)?);
+        }
+        let content = fs::read_to_string(&plain)?;
+        assert_eq!(content.lines().count(), 1);
+        // stdin 无法建立索引
+        assert!(process_csv_slice("-", "-", "0..1".parse()?, true).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use chrono::Local;
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use super::csv_batch::glob_base;
+
+/// 监听输入的文件、目录或者 glob，启动时以及每次变化之后调用 run
+/// run 失败时只打印一行错误，继续监听，直到 Ctrl-C 退出
+/// outputs 为输出的文件或目录，其中的变化不会触发转换，否则输出 csv 到监听的目录时会不停地重新转换
+pub fn process_csv_watch<F>(
+    input: &str,
+    outputs: &[PathBuf],
+    debounce: Duration,
+    mut run: F,
+) -> Result<()>
+where
+    F: FnMut() -> Result<()>,
+{
+    let target = WatchTarget::new(input)?;
+    let ignored = outputs
+        .iter()
+        .map(|output| target.check_output(output))
+        .collect::<Result<Vec<_>>>()?;
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(debounce, tx)?;
+    let (dir, mode) = target.watch_dir();
+    debouncer.watcher().watch(&dir, mode)?;
+    eprintln!("watching {} (Ctrl-C to stop)", input);
+
+    run_once(&mut run);
+    for result in rx {
+        match result {
+            Ok(events) => {
+                let relevant = events.iter().any(|e| {
+                    target.is_relevant(&e.path) && !ignored.iter().any(|o| e.path.starts_with(o))
+                });
+                if relevant {
+                    run_once(&mut run);
+                }
+            }
+            Err(e) => eprintln!("[{}] watch error: {}", Local::now().format("%H:%M:%S"), e),
+        }
+    }
+    Ok(())
+}
+
+/// 每次运行输出一行状态
+fn run_once<F: FnMut() -> Result<()>>(run: &mut F) {
+    let start = Instant::now();
+    let result = run();
+    let time = Local::now().format("%H:%M:%S");
+    let elapsed = start.elapsed().as_millis();
+    match result {
+        Ok(()) => eprintln!("[{}] ok in {}ms", time, elapsed),
+        Err(e) => eprintln!("[{}] failed in {}ms: {:#}", time, elapsed, e),
+    }
+}
+
+#[derive(Debug)]
+enum WatchTarget {
+    File(PathBuf),
+    // 目录或者 glob，其中任意 csv 文件变化时都重新转换
+    Dir(PathBuf),
+}
+
+impl WatchTarget {
+    fn new(input: &str) -> Result<Self> {
+        let path = Path::new(input);
+        let target = if path.is_file() {
+            WatchTarget::File(path.canonicalize()?)
+        } else if path.is_dir() {
+            WatchTarget::Dir(path.canonicalize()?)
+        } else {
+            let base = glob_base(input);
+            let base = if base.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                base
+            };
+            WatchTarget::Dir(base.canonicalize()?)
+        };
+        Ok(target)
+    }
+
+    /// 编辑器保存时常常是先写临时文件再 rename，所以单个文件时监听它所在的目录
+    fn watch_dir(&self) -> (PathBuf, RecursiveMode) {
+        match self {
+            WatchTarget::File(file) => (
+                file.parent().map(Path::to_path_buf).unwrap_or_default(),
+                RecursiveMode::NonRecursive,
+            ),
+            WatchTarget::Dir(dir) => (dir.clone(), RecursiveMode::Recursive),
+        }
+    }
+
+    /// 输出不能包含监听的输入 (例如输出目录就是输入目录)，否则忽略输出的变化之后，输入的变化也会被忽略
+    fn check_output(&self, output: &Path) -> Result<PathBuf> {
+        let output = output
+            .canonicalize()
+            .or_else(|_| std::path::absolute(output))?;
+        let (WatchTarget::File(watched) | WatchTarget::Dir(watched)) = self;
+        if watched.starts_with(&output) {
+            anyhow::bail!(
+                "Output {} contains the watched input, write the output outside of it",
+                output.display()
+            );
+        }
+        Ok(output)
+    }
+
+    fn is_relevant(&self, path: &Path) -> bool {
+        match self {
+            WatchTarget::File(file) => path == file,
+            WatchTarget::Dir(_) => path.extension().is_some_and(|ext| ext == "csv"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_target() -> Result<()> {
+        let target = WatchTarget::new("assets/juventus.csv")?;
+        let file = Path::new("assets/juventus.csv").canonicalize()?;
+        let (dir, mode) = target.watch_dir();
+        assert_eq!(dir, Path::new("assets").canonicalize()?);
+        assert_eq!(mode, RecursiveMode::NonRecursive);
+        assert!(target.is_relevant(&file));
+        assert!(!target.is_relevant(&dir.join("leagueOfLegends.csv")));
+
+        let target = WatchTarget::new("assets/*.csv")?;
+        assert!(target.is_relevant(&dir.join("leagueOfLegends.csv")));
+        assert!(!target.is_relevant(&dir.join("README.md")));
+
+        // 输出在监听的目录之下时忽略，包含监听的目录时报错
+        assert_eq!(
+            target.check_output(Path::new("assets/out"))?,
+            dir.join("out")
+        );
+        assert!(target.check_output(Path::new("assets")).is_err());
+        assert!(target.check_output(Path::new(".")).is_err());
+        Ok(())
+    }
+}
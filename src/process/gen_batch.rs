@@ -0,0 +1,222 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::{collections::HashSet, io::Write};
+use zxcvbn::zxcvbn;
+
+use super::gen_pwned::PwnedDb;
+
+/// 生成的一条密码，以及它的强度
+#[derive(Debug, Serialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+    /// zxcvbn 评分 0 - 4
+    pub score: u8,
+    pub guesses_log10: f64,
+    /// 按生成方式计算的熵，与 zxcvbn 的估计无关
+    pub entropy_bits: f64,
+}
+
+impl GeneratedPassword {
+    pub fn new(password: String, entropy: f64) -> Self {
+        let estimate = zxcvbn(&password, &[]);
+        Self {
+            score: estimate.score().into(),
+            guesses_log10: round2(estimate.guesses_log10()),
+            entropy_bits: round2(entropy),
+            password,
+        }
+    }
+}
+
+/// zxcvbn 评分 0 - 4 分别需要的 guesses_log10 下限
+const SCORE_GUESSES_LOG10: [f64; 5] = [0.0, 3.0, 6.0, 8.0, 10.0];
+
+/// 生成的密码需要达到的强度，达不到时重新生成
+#[derive(Debug, Default)]
+pub struct StrengthTarget {
+    pub min_score: u8,
+    pub min_entropy: f64,
+    /// 出现在泄露数据中的密码会被重新生成
+    pub pwned_db: Option<PwnedDb>,
+}
+
+impl StrengthTarget {
+    /// 熵是由生成方式决定的，不会因为重新生成而变化，达不到目标时直接返回错误
+    fn ensure_entropy(&self, entropy: f64) -> Result<()> {
+        if entropy < self.min_entropy {
+            anyhow::bail!(
+                "Entropy is {:.1} bits, which can never reach --min-entropy {}; increase the length or enable more characters",
+                entropy,
+                self.min_entropy
+            );
+        }
+        Ok(())
+    }
+
+    /// zxcvbn 的猜测次数不会超过逐字符暴力破解的 10^长度 次，评分的上限只由长度决定
+    /// 评分的阈值是 10^n + 5，因此长度需要大于 required
+    fn ensure_score_reachable(&self, password: &str) -> Result<()> {
+        let required = SCORE_GUESSES_LOG10[usize::from(self.min_score.min(4))];
+        let length = password.chars().count();
+        if self.min_score > 0 && length as f64 <= required {
+            anyhow::bail!(
+                "Passwords of {} characters can never reach --min-score {} (needs more than 10^{} guesses); increase the length",
+                length,
+                self.min_score,
+                required
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 批量生成 count 个互不相同、并且达到 target 的密码，generate 返回密码和熵 (bit)
+/// 密码空间太小 (例如 4 位 PIN) 时，重复过多会返回错误，而不是一直重试
+pub fn process_genbatch(
+    count: usize,
+    target: &StrengthTarget,
+    mut generate: impl FnMut() -> Result<(String, f64)>,
+) -> Result<Vec<GeneratedPassword>> {
+    if count == 0 {
+        anyhow::bail!("Count must be greater than 0");
+    }
+    let max_attempts = count.saturating_mul(10).saturating_add(100);
+    let mut seen = HashSet::with_capacity(count);
+    let mut passwords = Vec::with_capacity(count);
+    let mut weak = 0;
+    let mut pwned = 0;
+    for _ in 0..max_attempts {
+        let (password, entropy) = generate()?;
+        target.ensure_entropy(entropy)?;
+        target.ensure_score_reachable(&password)?;
+        if !seen.insert(password.clone()) {
+            continue;
+        }
+        let password = GeneratedPassword::new(password, entropy);
+        if password.score < target.min_score {
+            weak += 1;
+            continue;
+        }
+        if let Some(db) = &target.pwned_db {
+            if db.count(&password.password)? > 0 {
+                pwned += 1;
+                continue;
+            }
+        }
+        passwords.push(password);
+        if passwords.len() == count {
+            return Ok(passwords);
+        }
+    }
+    if weak > 0 {
+        anyhow::bail!(
+            "Only {} of {} passwords reached --min-score {} after {} attempts ({} scored lower, {} found in the pwned passwords file); increase the length or use a stronger generator",
+            passwords.len(),
+            count,
+            target.min_score,
+            max_attempts,
+            weak,
+            pwned
+        );
+    }
+    anyhow::bail!(
+        "Only {} of {} passwords could be generated after {} attempts ({} found in the pwned passwords file), the password space is too small",
+        passwords.len(),
+        count,
+        max_attempts,
+        pwned
+    )
+}
+
+/// 写出为 json 数组
+pub fn write_passwords_json(passwords: &[GeneratedPassword], writer: impl Write) -> Result<()> {
+    serde_json::to_writer_pretty(writer, passwords)?;
+    Ok(())
+}
+
+/// 写出为 csv，第一行为列名
+pub fn write_passwords_csv(passwords: &[GeneratedPassword], writer: impl Write) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for password in passwords {
+        writer.serialize(password)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_genbatch() -> Result<()> {
+        let mut i = 0;
+        let target = StrengthTarget::default();
+        let passwords = process_genbatch(3, &target, || {
+            i += 1;
+            // 每个密码重复一次，重复的会被跳过
+            Ok((format!("correct-horse-{}", i / 2), 30.0))
+        })?;
+        assert_eq!(passwords.len(), 3);
+        assert_eq!(passwords[2].password, "correct-horse-2");
+
+        let mut output = Vec::new();
+        write_passwords_csv(&passwords, &mut output)?;
+        let output = String::from_utf8(output)?;
+        assert!(output.starts_with("password,score,guesses_log10,entropy_bits\n"));
+        assert_eq!(output.lines().count(), 4);
+
+        assert!(process_genbatch(5, &target, || Ok(("same".to_string(), 0.0))).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genbatch_target() -> Result<()> {
+        let target = StrengthTarget {
+            min_score: 4,
+            min_entropy: 80.0,
+            pwned_db: None,
+        };
+        // 4 位 PIN 的熵只有 13 bit，第一次生成之后就返回错误
+        let mut attempts = 0;
+        let result = process_genbatch(1, &target, || {
+            attempts += 1;
+            Ok(("8351".to_string(), 13.3))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let target = StrengthTarget {
+            min_score: 4,
+            min_entropy: 0.0,
+            pwned_db: None,
+        };
+        // 8 个字符最多 10^8 次猜测，评分不可能达到 4
+        assert!(target.ensure_score_reachable("x7#Kp2!q").is_err());
+        assert!(target.ensure_score_reachable("x7#Kp2!qZ4m").is_ok());
+        let passwords = process_genbatch(1, &target, || {
+            Ok(("correct-horse-battery-staple".to_string(), 44.0))
+        })?;
+        assert_eq!(passwords[0].score, 4);
+        // 长度足够但评分一直达不到时，错误中说明是评分的问题
+        let err = process_genbatch(1, &target, || Ok(("passwordpassword".to_string(), 44.0)))
+            .unwrap_err();
+        assert!(err.to_string().contains("reached --min-score 4"));
+
+        // 出现在泄露数据中的密码会被重新生成
+        let target = StrengthTarget {
+            pwned_db: Some(PwnedDb::open("fixtures/pwned_sample.txt")?),
+            ..Default::default()
+        };
+        let mut candidates = ["letmein", "qwerty", "8351-tuba-ocean"].into_iter();
+        let passwords = process_genbatch(1, &target, || {
+            Ok((candidates.next().unwrap_or_default().to_string(), 40.0))
+        })?;
+        assert_eq!(passwords[0].password, "8351-tuba-ocean");
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{BufRead, BufReader};
+use zxcvbn::{matching::patterns::MatchPattern, zxcvbn};
+
+use super::gen_pwned::PwnedDb;
+use crate::utils::get_read;
+
+/// 一个密码的 zxcvbn 分析结果，只有 show 时才包含密码本身
+#[derive(Debug, Serialize)]
+pub struct PasswordReport {
+    /// 在输入中的行号，不输出密码时用来对应
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    pub score: u8,
+    pub guesses_log10: f64,
+    pub crack_times: CrackTimes,
+    pub patterns: Vec<String>,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+    /// 在泄露数据中出现的次数，只有指定了 pwned 密码库时才有
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pwned: Option<u64>,
+}
+
+/// 不同攻击场景下的破解时间
+#[derive(Debug, Serialize)]
+pub struct CrackTimes {
+    pub online_throttling_100_per_hour: String,
+    pub online_no_throttling_10_per_second: String,
+    pub offline_slow_hashing_1e4_per_second: String,
+    pub offline_fast_hashing_1e10_per_second: String,
+}
+
+/// 分析一个密码，user_inputs 为用户名、网站名等容易被猜到的单词
+/// show 为 false 时，匹配到的模式也不会包含密码片段
+pub fn check_password(
+    line: usize,
+    password: &str,
+    user_inputs: &[&str],
+    show: bool,
+) -> PasswordReport {
+    let estimate = zxcvbn(password, user_inputs);
+    let times = estimate.crack_times();
+    let patterns = estimate
+        .sequence()
+        .iter()
+        .map(|m| {
+            let name = match &m.pattern {
+                MatchPattern::Dictionary(p) if p.reversed => "reversed dictionary word",
+                MatchPattern::Dictionary(p) if p.l33t => "l33t dictionary word",
+                MatchPattern::Dictionary(_) => "dictionary word",
+                MatchPattern::Spatial(_) => "keyboard pattern",
+                MatchPattern::Repeat(_) => "repeat",
+                MatchPattern::Sequence(_) => "sequence",
+                MatchPattern::Regex(_) => "recent year",
+                MatchPattern::Date(_) => "date",
+                MatchPattern::BruteForce => "bruteforce",
+            };
+            if show {
+                format!("{} ({})", name, m.token)
+            } else {
+                name.to_string()
+            }
+        })
+        .collect();
+    let feedback = estimate.feedback();
+    PasswordReport {
+        line,
+        password: show.then(|| password.to_string()),
+        score: estimate.score().into(),
+        guesses_log10: (estimate.guesses_log10() * 100.0).round() / 100.0,
+        crack_times: CrackTimes {
+            online_throttling_100_per_hour: times.online_throttling_100_per_hour().to_string(),
+            online_no_throttling_10_per_second: times
+                .online_no_throttling_10_per_second()
+                .to_string(),
+            offline_slow_hashing_1e4_per_second: times
+                .offline_slow_hashing_1e4_per_second()
+                .to_string(),
+            offline_fast_hashing_1e10_per_second: times
+                .offline_fast_hashing_1e10_per_second()
+                .to_string(),
+        },
+        patterns,
+        warning: feedback.and_then(|f| f.warning()).map(|w| w.to_string()),
+        suggestions: feedback
+            .map(|f| f.suggestions().iter().map(|s| s.to_string()).collect())
+            .unwrap_or_default(),
+        pwned: None,
+    }
+}
+
+/// 从 stdin 或者文件中读取密码 (每行一个)，跳过空行
+/// 指定了 pwned_db 时同时查询密码在泄露数据中出现的次数
+pub fn process_genpass_check(
+    input: &str,
+    user_inputs: &[String],
+    show: bool,
+    pwned_db: Option<&PwnedDb>,
+) -> Result<Vec<PasswordReport>> {
+    let user_inputs: Vec<&str> = user_inputs.iter().map(String::as_str).collect();
+    let reader = BufReader::new(get_read(input)?);
+    let mut reports = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let password = line.strip_suffix('\r').unwrap_or(&line);
+        if password.is_empty() {
+            continue;
+        }
+        let mut report = check_password(i + 1, password, &user_inputs, show);
+        if let Some(db) = pwned_db {
+            report.pwned = Some(db.count(password)?);
+        }
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_password() {
+        let report = check_password(1, "password123", &[], false);
+        assert_eq!(report.score, 0);
+        assert!(report.password.is_none());
+        assert!(report.warning.is_some());
+        assert!(report.patterns.iter().all(|p| !p.contains("password")));
+
+        // 用户名会降低包含它的密码的评分
+        let without = check_password(1, "jsmith1987!Tq", &[], true);
+        let with = check_password(1, "jsmith1987!Tq", &["jsmith"], true);
+        assert!(with.guesses_log10 < without.guesses_log10);
+        assert_eq!(with.password.as_deref(), Some("jsmith1987!Tq"));
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha20Rng,
+};
+
+use super::{
+    gen_pass::PasswordRules,
+    gen_pattern::{parse_pattern, PatternItem},
+    gen_policy::PasswordPolicy,
+};
+
+/// 不符合策略时，继续从同一个字节流中重新生成的次数上限
+const MAX_ATTEMPTS: usize = 100;
+
+/// 无状态的站点密码派生参数，相同的参数和主密码总是得到相同的密码
+#[derive(Debug)]
+pub struct DeriveOptions {
+    pub site: String,
+    pub login: String,
+    /// 需要更换密码时加一
+    pub counter: u32,
+    /// 与 --pattern 相同格式的模板，不指定时按字符类规则生成
+    pub template: Option<String>,
+    /// Argon2id 的内存 (KiB) 和迭代次数，修改之后派生出来的密码都会变化
+    pub memory_kib: u32,
+    pub iterations: u32,
+}
+
+/// 用 Argon2id 从主密码派生出站点密码，不保存任何状态
+/// 派生出来的字节映射到与 process_genpass 相同的字符类和规则上，policy 不为 None 时结果也会符合策略
+pub fn process_genderive(
+    master: &str,
+    opts: &DeriveOptions,
+    rules: &PasswordRules,
+    policy: Option<&PasswordPolicy>,
+) -> Result<String> {
+    if master.is_empty() {
+        anyhow::bail!("Master password must not be empty");
+    }
+    if opts.site.trim().is_empty() {
+        anyhow::bail!("Site must not be empty");
+    }
+    let mut stream = DerivedStream::new(master, opts)?;
+
+    if let Some(template) = &opts.template {
+        let password = parse_pattern(template, rules)?
+            .iter()
+            .map(|item| match item {
+                PatternItem::Literal(c) => *c,
+                PatternItem::Chars(set) => stream.choose(set) as char,
+            })
+            .collect();
+        return Ok(password);
+    }
+
+    let mut violations = Vec::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let password = stream.password(rules)?;
+        match policy {
+            Some(policy) => violations = policy.check(&password)?,
+            None => return Ok(password),
+        }
+        if violations.is_empty() {
+            return Ok(password);
+        }
+    }
+    anyhow::bail!(
+        "Could not derive a password that satisfies the policy after {} attempts: {}",
+        MAX_ATTEMPTS,
+        violations.join(", ")
+    )
+}
+
+/// 以 Argon2id 的输出作为种子的 ChaCha20 字节流
+/// 没有使用 rand 的 gen_range / shuffle，它们的算法在 rand 的版本之间可能变化，而派生出来的密码必须保持稳定
+struct DerivedStream(ChaCha20Rng);
+
+impl DerivedStream {
+    fn new(master: &str, opts: &DeriveOptions) -> Result<Self> {
+        // 站点名不区分大小写，字段之间用 \0 分隔，避免 ("ab", "c") 和 ("a", "bc") 得到相同的 salt
+        let salt = format!(
+            "rcli-derive\0{}\0{}\0{}",
+            opts.site.trim().to_lowercase(),
+            opts.login,
+            opts.counter
+        );
+        let params = Params::new(opts.memory_kib, opts.iterations, 1, Some(32))
+            .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+        let mut seed = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(master.as_bytes(), salt.as_bytes(), &mut seed)
+            .map_err(|e| anyhow::anyhow!("Failed to derive the password: {}", e))?;
+        Ok(Self(ChaCha20Rng::from_seed(seed)))
+    }
+
+    /// 拒绝采样，返回 0..n 之间均匀分布的数
+    fn index(&mut self, n: usize) -> usize {
+        let n = n as u32;
+        let limit = u32::MAX - u32::MAX % n;
+        loop {
+            let value = self.0.next_u32();
+            if value < limit {
+                return (value % n) as usize;
+            }
+        }
+    }
+
+    fn choose(&mut self, set: &[u8]) -> u8 {
+        set[self.index(set.len())]
+    }
+
+    /// 与 process_genpass 相同的生成方式：先满足每一类字符的最少个数，再补全长度，最后打乱
+    fn password(&mut self, rules: &PasswordRules) -> Result<String> {
+        let classes = rules.classes()?;
+        let mut password = Vec::with_capacity(rules.length);
+        let mut chars = Vec::new();
+        for class in &classes {
+            chars.extend_from_slice(&class.chars);
+            for _ in 0..class.min {
+                password.push(self.choose(&class.chars));
+            }
+        }
+        chars.sort_unstable();
+        chars.dedup();
+        for _ in password.len()..rules.length {
+            password.push(self.choose(&chars));
+        }
+        // Fisher-Yates
+        for i in (1..password.len()).rev() {
+            let j = self.index(i + 1);
+            password.swap(i, j);
+        }
+        Ok(String::from_utf8(password)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(counter: u32, template: Option<&str>) -> DeriveOptions {
+        // 测试中使用较小的内存，加快速度
+        DeriveOptions {
+            site: "Example.com".into(),
+            login: "alice".into(),
+            counter,
+            template: template.map(Into::into),
+            memory_kib: 64,
+            iterations: 1,
+        }
+    }
+
+    #[test]
+    fn test_process_genderive() -> Result<()> {
+        let rules = PasswordRules::default();
+        let first = process_genderive("master", &options(1, None), &rules, None)?;
+        let again = process_genderive("master", &options(1, None), &rules, None)?;
+        assert_eq!(first, again);
+        assert_eq!(first.len(), 16);
+        assert!(rules
+            .charsets()?
+            .iter()
+            .all(|class| first.bytes().any(|b| class.chars.contains(&b))));
+
+        assert_ne!(
+            first,
+            process_genderive("master", &options(2, None), &rules, None)?
+        );
+        assert_ne!(
+            first,
+            process_genderive("Master", &options(1, None), &rules, None)?
+        );
+
+        let pin = process_genderive("master", &options(1, Some("9999-\\A")), &rules, None)?;
+        assert!(pin[..4].bytes().all(|b| b.is_ascii_digit()));
+        assert!(pin.ends_with("-A"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genderive_known_answers() -> Result<()> {
+        // 派生出来的密码是兼容性约定：字符集、拒绝采样或者 salt 的格式变化都会改变所有用户的站点密码
+        let rules = PasswordRules {
+            length: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            process_genderive("master", &options(1, None), &rules, None)?,
+            "S9$ijFZJTH!3MUSnP^MW"
+        );
+        let rules = PasswordRules {
+            symbol: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            process_genderive("master", &options(2, None), &rules, None)?,
+            "SM2ByfqH7GJeDxTb"
+        );
+        assert_eq!(
+            process_genderive("master", &options(1, Some("aaaa-9999-\\A")), &rules, None)?,
+            "ynbc-6863-A"
+        );
+        Ok(())
+    }
+}
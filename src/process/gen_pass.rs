@@ -1,64 +1,180 @@
-use anyhow::Ok;
+use anyhow::Result;
 use rand::seq::SliceRandom;
 
-// 生命周期为static
+// 生命周期为static，默认去掉了容易混淆的字符 (I O l 0)
 const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
 const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
-const NUMBER: &[u8] = b"123456789";
-const SYMBOL: &[u8] = b"!@#$%^&*_";
-
-pub fn process_genpass(
-    length: u8,
-    upper: bool,
-    lower: bool,
-    number: bool,
-    symbol: bool,
-) -> anyhow::Result<String> {
-    let mut rng = rand::thread_rng();
-    // let mut password = String::new(); // shuffle  不支持String，因此这里修改成Vec
-    let mut password = Vec::new();
-    let mut chars = Vec::new();
+pub(crate) const NUMBER: &[u8] = b"123456789";
+pub(crate) const SYMBOL: &[u8] = b"!@#$%^&*_";
 
-    if upper {
-        chars.extend_from_slice(UPPER);
-        // password.push(*UPPER.choose(&mut rng).expect("UPPER won't be empty") as char);
-        password.push(*UPPER.choose(&mut rng).expect("UPPER won't be empty"));
+// 容易混淆的字符，--include-ambiguous 时加回对应的字符集中
+const AMBIGUOUS_UPPER: &[u8] = b"IO";
+const AMBIGUOUS_LOWER: &[u8] = b"l";
+const AMBIGUOUS_NUMBER: &[u8] = b"0";
+
+/// 字符密码的生成规则
+#[derive(Debug, Clone)]
+pub struct PasswordRules {
+    pub length: usize,
+    pub upper: bool,
+    pub lower: bool,
+    pub number: bool,
+    pub symbol: bool,
+    /// 自定义的特殊字符集，替换默认的 SYMBOL
+    pub symbols_set: Option<String>,
+    /// 从所有字符集中去掉的字符
+    pub exclude: String,
+    pub include_ambiguous: bool,
+    // 每一类字符至少出现的次数，启用的字符类至少出现一次
+    pub min_upper: usize,
+    pub min_lower: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+}
+
+impl Default for PasswordRules {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            upper: true,
+            lower: true,
+            number: true,
+            symbol: true,
+            symbols_set: None,
+            exclude: String::new(),
+            include_ambiguous: false,
+            min_upper: 0,
+            min_lower: 0,
+            min_digits: 0,
+            min_symbols: 0,
+        }
     }
+}
 
-    if lower {
-        chars.extend_from_slice(LOWER);
-        // password.push(*LOWER.choose(&mut rng).expect("LOWER won't be empty") as char);
-        password.push(*LOWER.choose(&mut rng).expect("LOWER won't be empty"));
+/// 一类字符，以及它在密码中至少出现的次数
+#[derive(Debug)]
+pub(crate) struct CharClass {
+    pub(crate) name: &'static str,
+    pub(crate) chars: Vec<u8>,
+    pub(crate) min: usize,
+}
+
+impl PasswordRules {
+    /// 计算出每一类启用的字符的字符集，不可能满足的规则返回错误
+    pub(crate) fn charsets(&self) -> Result<Vec<CharClass>> {
+        let symbols = match &self.symbols_set {
+            Some(set) => {
+                if set.is_empty() || !set.bytes().all(|b| b.is_ascii_graphic()) {
+                    anyhow::bail!("--symbols-set must contain printable ascii characters only");
+                }
+                set.as_bytes()
+            }
+            None => SYMBOL,
+        };
+        let ambiguous = |chars: &[u8], extra: &[u8]| {
+            let mut chars = chars.to_vec();
+            if self.include_ambiguous {
+                chars.extend_from_slice(extra);
+            }
+            chars
+        };
+        let candidates = [
+            (
+                "upper",
+                self.upper,
+                ambiguous(UPPER, AMBIGUOUS_UPPER),
+                self.min_upper,
+            ),
+            (
+                "lower",
+                self.lower,
+                ambiguous(LOWER, AMBIGUOUS_LOWER),
+                self.min_lower,
+            ),
+            (
+                "digits",
+                self.number,
+                ambiguous(NUMBER, AMBIGUOUS_NUMBER),
+                self.min_digits,
+            ),
+            ("symbols", self.symbol, symbols.to_vec(), self.min_symbols),
+        ];
+
+        let mut classes = Vec::new();
+        for (name, enabled, mut chars, min) in candidates {
+            if !enabled {
+                if min > 0 {
+                    anyhow::bail!("--min-{} is set but {} are disabled", name, name);
+                }
+                continue;
+            }
+            chars.retain(|c| !self.exclude.as_bytes().contains(c));
+            chars.sort_unstable();
+            chars.dedup();
+            if chars.is_empty() {
+                anyhow::bail!("No {} left after --exclude", name);
+            }
+            classes.push(CharClass {
+                name,
+                chars,
+                min: min.max(1),
+            });
+        }
+
+        if classes.is_empty() {
+            anyhow::bail!("At least one character class must be enabled");
+        }
+        Ok(classes)
     }
 
-    if number {
-        chars.extend_from_slice(NUMBER);
-        // password.push(*NUMBER.choose(&mut rng).expect("NUMBER won't be empty") as char);
-        password.push(*NUMBER.choose(&mut rng).expect("NUMBER won't be empty"));
+    /// 在 charsets 的基础上检查长度是否满足每一类字符的最少个数
+    pub(crate) fn classes(&self) -> Result<Vec<CharClass>> {
+        let classes = self.charsets()?;
+        let required: usize = classes.iter().map(|c| c.min).sum();
+        if self.length < required {
+            let detail = classes
+                .iter()
+                .map(|c| format!("{} {}", c.min, c.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            anyhow::bail!(
+                "Length {} is too short, the rules require at least {} characters ({})",
+                self.length,
+                required,
+                detail
+            );
+        }
+        Ok(classes)
     }
 
-    if symbol {
-        chars.extend_from_slice(SYMBOL); //尽可能使用不会产生歧义的特殊字符
-        password.push(*SYMBOL.choose(&mut rng).expect("SYMBOL won't be empty"));
+    /// 按实际的字符集大小和长度计算熵 (bit)
+    pub fn entropy(&self) -> Result<f64> {
+        let mut chars: Vec<u8> = self.classes()?.into_iter().flat_map(|c| c.chars).collect();
+        chars.sort_unstable();
+        chars.dedup();
+        Ok(self.length as f64 * (chars.len() as f64).log2())
     }
+}
 
-    // for _ in 0..length {
-    //     // 这种方案，就是上面将用户需要的所有可能性，都压入 chars的序列内，然后通过choose崇中random筛选一部分，因此，可能会导致
-    //     // 有一定的概率筛选出来的密码，不包含用户所需要的要素，例如，有可能筛选出来一部分，是完全没有数字，或者特殊字符的，
-    //     // 可是使用一种方案比如预设某几位一定是数组，特殊字符，大小写等，让这些要素尽可能都存在
-
-    //     // 或者从每样ranom中获取一部分，再去拼接
-    //     let c = chars
-    //         .choose(&mut rng)
-    //         .expect("chars won't be empty in the context");
-    //     password.push(*c as char); // u8属于可拷贝的数据类型，可以通过*c 获取
-    // }
-
-    // 这个思路很简单， 先从上述每个选项中固定取 例如，用户配置了哪些要素，就先取一个要素放进去
-    // 此时 password 已经有4位了(假设用户将4个要素都配置了)，那么在这个for这里，就把上面预先给了的位置剪掉，在用下面这一段将剩余的长度补全，但是
-    // 这种生成出来的密码，前面4位(假设用户将4个要素都配置了) 的元素类型是固定的，因此，我们需要在生成密码之后，将结果随机分部一下
-    for _ in 0..(length - password.len() as u8) {
-        //  使用 as u8强制转换
+pub fn process_genpass(rules: &PasswordRules) -> Result<String> {
+    let classes = rules.classes()?;
+    let mut rng = rand::thread_rng();
+    // shuffle 不支持String，因此这里使用Vec
+    let mut password = Vec::with_capacity(rules.length);
+    let mut chars = Vec::new();
+
+    // 先从每类字符中取出至少需要的个数，再从所有字符中补全剩余的长度
+    // 这样生成出来的密码，前面几位的类型是固定的，因此最后需要随机排布一下
+    for class in &classes {
+        chars.extend_from_slice(&class.chars);
+        for _ in 0..class.min {
+            password.push(*class.chars.choose(&mut rng).expect("class won't be empty"));
+        }
+    }
+    // 字符集之间可能有重复 (例如 --symbols-set 中包含字母)
+    chars.sort_unstable();
+    chars.dedup();
+    for _ in password.len()..rules.length {
         let c = chars
             .choose(&mut rng)
             .expect("chars won't be empty in the context");
@@ -69,7 +185,69 @@ pub fn process_genpass(
     password.shuffle(&mut rng);
 
     let password_result = String::from_utf8(password)?;
-    // println!("{}", password_result);
-    // output password strength in stderr
     Ok(password_result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_genpass_minimums() -> Result<()> {
+        let rules = PasswordRules {
+            length: 12,
+            symbols_set: Some("-_.".into()),
+            exclude: "abc".into(),
+            min_digits: 3,
+            min_symbols: 2,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let password = process_genpass(&rules)?;
+            assert_eq!(password.len(), 12);
+            assert!(password.bytes().filter(u8::is_ascii_digit).count() >= 3);
+            assert!(password.bytes().filter(|c| b"-_.".contains(c)).count() >= 2);
+            assert!(!password.contains(['a', 'b', 'c', '!']));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpass_invalid_rules() {
+        // 以前 length 小于启用的字符类数量时会溢出 panic
+        let short = PasswordRules {
+            length: 3,
+            ..Default::default()
+        };
+        assert!(process_genpass(&short).is_err());
+
+        let excluded = PasswordRules {
+            exclude: "123456789".into(),
+            ..Default::default()
+        };
+        assert!(process_genpass(&excluded).is_err());
+
+        let disabled = PasswordRules {
+            number: false,
+            min_digits: 2,
+            ..Default::default()
+        };
+        assert!(process_genpass(&disabled).is_err());
+    }
+
+    #[test]
+    fn test_password_rules_entropy() -> Result<()> {
+        // 默认 24 + 25 + 9 + 9 = 67 个字符
+        let rules = PasswordRules::default();
+        assert!((rules.entropy()? - 16.0 * 67f64.log2()).abs() < 1e-9);
+        let digits = PasswordRules {
+            length: 10,
+            upper: false,
+            lower: false,
+            symbol: false,
+            ..Default::default()
+        };
+        assert!((digits.entropy()? - 10.0 * 9f64.log2()).abs() < 1e-9);
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use rand::{seq::SliceRandom, Rng};
+
+use super::gen_pass::PasswordRules;
+
+/// 最常见的 PIN，来自公开的泄露数据统计
+const COMMON_PINS: &[&str] = &[
+    "1234", "1111", "0000", "1212", "7777", "1004", "2000", "4444", "2222", "6969", "9999", "3333",
+    "5555", "6666", "1122", "1313", "8888", "4321", "2001", "1010", "2580", "123456", "111111",
+    "000000", "123123", "666666", "121212", "112233", "789456", "159753", "654321", "987654",
+    "696969", "123321", "131313", "777777", "555555", "999999", "888888", "222222", "147258",
+];
+
+/// 弱 PIN 时重新生成的次数上限
+const MAX_ATTEMPTS: usize = 1000;
+
+/// 模板中的一个位置
+pub(crate) enum PatternItem {
+    Literal(char),
+    Chars(Vec<u8>),
+}
+
+/// 解析模板，例如 "Aaaa-9999-!!??"
+/// A 大写字母, a 小写字母, 9 数字, ! 特殊字符, ? 任意启用的字符类，其他字符原样输出
+/// 使用 \ 转义，例如 "\A" 输出 A
+pub(crate) fn parse_pattern(pattern: &str, rules: &PasswordRules) -> Result<Vec<PatternItem>> {
+    let charsets = rules.charsets()?;
+    let class = |name: &str, placeholder: char| {
+        charsets
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| PatternItem::Chars(c.chars.clone()))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Pattern uses `{}` but {} are disabled", placeholder, name)
+            })
+    };
+    let mut any: Vec<u8> = charsets.iter().flat_map(|c| c.chars.clone()).collect();
+    any.sort_unstable();
+    any.dedup();
+
+    let mut items = Vec::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        items.push(match c {
+            'A' => class("upper", c)?,
+            'a' => class("lower", c)?,
+            '9' => class("digits", c)?,
+            '!' => class("symbols", c)?,
+            '?' => PatternItem::Chars(any.clone()),
+            '\\' => PatternItem::Literal(
+                chars
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Pattern ends with a dangling `\\`"))?,
+            ),
+            _ => PatternItem::Literal(c),
+        });
+    }
+    // 全是字面量的模板每次都输出同样的内容
+    if !items
+        .iter()
+        .any(|item| matches!(item, PatternItem::Chars(_)))
+    {
+        anyhow::bail!(
+            "Pattern `{}` has no placeholders (A, a, 9, ! or ?), it would always produce the same output",
+            pattern
+        );
+    }
+    Ok(items)
+}
+
+/// 按模板生成，返回密码和熵 (bit)，模板的格式见 parse_pattern
+pub fn process_genpattern(pattern: &str, rules: &PasswordRules) -> Result<(String, f64)> {
+    let mut rng = rand::thread_rng();
+    let mut password = String::with_capacity(pattern.len());
+    let mut entropy = 0.0;
+    for item in parse_pattern(pattern, rules)? {
+        match item {
+            PatternItem::Literal(c) => password.push(c),
+            PatternItem::Chars(set) => {
+                password.push(*set.choose(&mut rng).expect("charset won't be empty") as char);
+                entropy += (set.len() as f64).log2();
+            }
+        }
+    }
+    Ok((password, entropy))
+}
+
+/// 生成 length 位的数字 PIN，重复、顺序、生日以及常见的 PIN 会被重新生成
+/// 返回 PIN 和熵 (bit)，熵按排除弱 PIN 之后剩余的空间计算 (保守估计)
+pub fn process_genpin(length: usize) -> Result<(String, f64)> {
+    if !(4..=12).contains(&length) {
+        anyhow::bail!("PIN length must be between 4 and 12");
+    }
+    let space = 10u64.pow(length as u32) - weak_pin_bound(length);
+    let mut rng = rand::thread_rng();
+    for _ in 0..MAX_ATTEMPTS {
+        let pin: String = (0..length)
+            .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+            .collect();
+        if weak_pin_reason(&pin).is_none() {
+            return Ok((pin, (space as f64).log2()));
+        }
+    }
+    anyhow::bail!(
+        "Could not generate a strong PIN after {} attempts",
+        MAX_ATTEMPTS
+    )
+}
+
+/// length 位 PIN 中被 weak_pin_reason 排除的数量的上界，各类之间的重叠重复计算
+fn weak_pin_bound(length: usize) -> u64 {
+    // 由长度为 p 的一段重复组成
+    let repeated: u64 = (1..length)
+        .filter(|p| length.is_multiple_of(*p))
+        .map(|p| 10u64.pow(p as u32))
+        .sum();
+    // 首位和公差各 10 种
+    let sequences = 100;
+    // 一年最多 366 天，见 looks_like_date
+    let dates = match length {
+        4 => 366 * 2 + 200,
+        6 => 366 * 100 * 3,
+        8 => 366 * 200 * 3,
+        _ => 0,
+    };
+    COMMON_PINS.len() as u64 + repeated + sequences + dates
+}
+
+/// PIN 为弱 PIN 时返回原因
+fn weak_pin_reason(pin: &str) -> Option<&'static str> {
+    let digits: Vec<i8> = pin.bytes().map(|b| (b - b'0') as i8).collect();
+    let n = digits.len();
+    if COMMON_PINS.contains(&pin) {
+        return Some("common PIN");
+    }
+    // 整体由一段重复组成，例如 1111, 1212, 123123
+    if (1..=n / 2).any(|p| n.is_multiple_of(p) && digits.chunks(p).all(|c| c == &digits[..p])) {
+        return Some("repeated digits");
+    }
+    // 等差数列 (允许 9 -> 0 回绕)，例如 1234, 9876, 7890, 2468
+    let step = (digits[1] - digits[0]).rem_euclid(10);
+    if digits
+        .windows(2)
+        .all(|w| (w[1] - w[0]).rem_euclid(10) == step)
+    {
+        return Some("sequence");
+    }
+    if looks_like_date(pin) {
+        return Some("date");
+    }
+    None
+}
+
+/// 4 位: MMDD, DDMM, 19xx/20xx 年份；6 位: DDMMYY, MMDDYY, YYMMDD；8 位: DDMMYYYY, MMDDYYYY, YYYYMMDD
+fn looks_like_date(pin: &str) -> bool {
+    let num = |range: std::ops::Range<usize>| pin[range].parse::<u32>().unwrap_or(0);
+    // 两位年份按 20xx 处理 (2000 是闰年，02-29 也算作日期)
+    let valid = |y: u32, m: u32, d: u32| NaiveDate::from_ymd_opt(y as i32, m, d).is_some();
+    let year = |y: u32| (1900..=2099).contains(&y);
+    match pin.len() {
+        4 => {
+            valid(2000, num(0..2), num(2..4))
+                || valid(2000, num(2..4), num(0..2))
+                || year(num(0..4))
+        }
+        6 => {
+            valid(2000 + num(4..6), num(2..4), num(0..2))
+                || valid(2000 + num(4..6), num(0..2), num(2..4))
+                || valid(2000 + num(0..2), num(2..4), num(4..6))
+        }
+        8 => {
+            (year(num(4..8)) && valid(num(4..8), num(2..4), num(0..2)))
+                || (year(num(4..8)) && valid(num(4..8), num(0..2), num(2..4)))
+                || (year(num(0..4)) && valid(num(0..4), num(4..6), num(6..8)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_genpattern() -> Result<()> {
+        let rules = PasswordRules::default();
+        let (password, entropy) = process_genpattern("Aaaa-9999-!!??-\\A", &rules)?;
+        let bytes = password.as_bytes();
+        assert_eq!(password.len(), 16);
+        assert!(bytes[0].is_ascii_uppercase());
+        assert!(bytes[1..4].iter().all(u8::is_ascii_lowercase));
+        assert_eq!(bytes[4], b'-');
+        assert!(bytes[5..9].iter().all(u8::is_ascii_digit));
+        assert!(password.ends_with("-A"));
+        assert!(entropy > 45.0 && entropy < 55.0);
+
+        let rules = PasswordRules {
+            symbol: false,
+            ..Default::default()
+        };
+        assert!(process_genpattern("!!", &rules).is_err());
+        assert!(process_genpattern("", &rules).is_err());
+        assert!(process_genpattern("id-9", &PasswordRules::default()).is_ok());
+        assert!(process_genpattern("\\A\\a-\\9", &PasswordRules::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_weak_pin_reason() -> Result<()> {
+        assert_eq!(weak_pin_reason("1234"), Some("common PIN"));
+        assert_eq!(weak_pin_reason("4747"), Some("repeated digits"));
+        assert_eq!(weak_pin_reason("890123"), Some("sequence"));
+        assert_eq!(weak_pin_reason("2468"), Some("sequence"));
+        assert_eq!(weak_pin_reason("1225"), Some("date"));
+        assert_eq!(weak_pin_reason("1987"), Some("date"));
+        assert_eq!(weak_pin_reason("180490"), Some("date"));
+        assert_eq!(weak_pin_reason("19900418"), Some("date"));
+        assert_eq!(weak_pin_reason("8351"), None);
+
+        let (pin, entropy) = process_genpin(6)?;
+        assert_eq!(pin.len(), 6);
+        // 6 位 PIN 中日期约占 10%，熵低于 6 * log2(10)
+        assert!(entropy > 19.0 && entropy < 6.0 * 10f64.log2());
+        assert!(weak_pin_reason(&pin).is_none());
+        assert!(process_genpin(3).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use rand::{seq::SliceRandom, Rng};
+use std::{collections::BTreeSet, fs};
+
+use super::gen_pass::{NUMBER, SYMBOL};
+
+/// 内置的 BIP-39 英文单词表，2048 个单词，每个单词 11 bit
+const DEFAULT_WORDLIST: &str = include_str!("../../assets/bip39_english.txt");
+
+/// diceware 口令的生成参数
+#[derive(Debug)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    /// 在随机一个单词后面插入一个数字，满足密码组成规则
+    pub digit: bool,
+    pub symbol: bool,
+}
+
+/// 读取单词表，path 为 None 时使用内置的单词表
+/// 自定义单词表每行一个单词，兼容 EFF 的 "11111\tabacus" 格式
+/// 批量生成时只需要读取一次
+pub fn load_wordlist(path: Option<&str>) -> Result<Vec<String>> {
+    let content = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => DEFAULT_WORDLIST.to_string(),
+    };
+    let wordlist: Vec<String> = parse_wordlist(&content)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    if wordlist.len() < 2 {
+        anyhow::bail!("Wordlist must contain at least 2 unique words");
+    }
+    Ok(wordlist)
+}
+
+/// 从 wordlist 中生成 diceware 口令，返回口令和熵 (bit)
+/// thread_rng 是 CSPRNG (ChaCha)，可以用来生成口令
+pub fn process_genphrase(wordlist: &[String], opts: &PassphraseOptions) -> Result<(String, f64)> {
+    if wordlist.len() < 2 {
+        anyhow::bail!("Wordlist must contain at least 2 unique words");
+    }
+    if opts.words == 0 {
+        anyhow::bail!("Number of words must be greater than 0");
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut words: Vec<String> = (0..opts.words)
+        .map(|_| {
+            let word = wordlist.choose(&mut rng).expect("wordlist won't be empty");
+            if opts.capitalize {
+                capitalize(word)
+            } else {
+                word.clone()
+            }
+        })
+        .collect();
+    // 大写是固定的规则，不增加熵；插入的字符只计算字符本身的熵，不计算插入的位置
+    let mut entropy = opts.words as f64 * (wordlist.len() as f64).log2();
+    for (enabled, chars) in [(opts.digit, NUMBER), (opts.symbol, SYMBOL)] {
+        if enabled {
+            let c = *chars.choose(&mut rng).expect("chars won't be empty") as char;
+            let i = rng.gen_range(0..words.len());
+            words[i].push(c);
+            entropy += (chars.len() as f64).log2();
+        }
+    }
+    Ok((words.join(&opts.separator), entropy))
+}
+
+/// 去掉空行和重复的单词，EFF 单词表中每行前面的骰子编号也会被去掉
+fn parse_wordlist(content: &str) -> Vec<&str> {
+    content
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wordlist() {
+        assert_eq!(parse_wordlist(DEFAULT_WORDLIST).len(), 2048);
+        assert_eq!(
+            parse_wordlist("11111\tabacus\n11112\tabdomen\n\n11113\tabacus\n"),
+            vec!["abacus", "abdomen"]
+        );
+    }
+
+    #[test]
+    fn test_process_genphrase() -> Result<()> {
+        let wordlist = load_wordlist(None)?;
+        let opts = PassphraseOptions {
+            words: 6,
+            separator: "-".into(),
+            capitalize: true,
+            digit: true,
+            symbol: false,
+        };
+        let (phrase, entropy) = process_genphrase(&wordlist, &opts)?;
+        let words: Vec<&str> = phrase.split('-').collect();
+        assert_eq!(words.len(), 6);
+        assert!(words.iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert_eq!(phrase.chars().filter(char::is_ascii_digit).count(), 1);
+        assert!((entropy - (66.0 + 9f64.log2())).abs() < 1e-9);
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::{collections::BTreeMap, fs};
+
+use super::gen_pass::{process_genpass, PasswordRules};
+
+/// 内置的密码策略
+const BUILTIN_POLICIES: &str = include_str!("../../assets/password_policies.yaml");
+
+/// 违反 forbidden / max_repeat 时重新生成的次数上限
+const MAX_ATTEMPTS: usize = 100;
+
+/// 一类字符在策略中的要求
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClassRule {
+    Required,
+    #[default]
+    Allowed,
+    Forbidden,
+}
+
+/// 命名的密码策略，描述长度范围、字符类以及禁止出现的模式
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PasswordPolicy {
+    #[serde(default)]
+    pub description: String,
+    pub min_length: usize,
+    pub max_length: Option<usize>,
+    /// 未指定 --length 时生成的长度
+    pub length: Option<usize>,
+    #[serde(default)]
+    pub upper: ClassRule,
+    #[serde(default)]
+    pub lower: ClassRule,
+    #[serde(default)]
+    pub digits: ClassRule,
+    #[serde(default)]
+    pub symbols: ClassRule,
+    /// 允许使用的特殊字符
+    pub symbol_set: Option<String>,
+    /// 同一个字符最多连续出现的次数
+    pub max_repeat: Option<usize>,
+    /// 加载策略时编译，避免每次检查 (以及重新生成时) 都编译一次
+    #[serde(default, deserialize_with = "deserialize_patterns")]
+    pub forbidden: Vec<Regex>,
+}
+
+fn deserialize_patterns<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Regex>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// 按名字查找策略，file 中的策略会覆盖同名的内置策略
+pub fn load_policy(name: &str, file: Option<&str>) -> Result<PasswordPolicy> {
+    let mut policies: BTreeMap<String, PasswordPolicy> = serde_yaml::from_str(BUILTIN_POLICIES)?;
+    if let Some(file) = file {
+        let custom: BTreeMap<String, PasswordPolicy> =
+            serde_yaml::from_str(&fs::read_to_string(file)?)?;
+        policies.extend(custom);
+    }
+    let names = policies.keys().cloned().collect::<Vec<_>>().join(", ");
+    policies
+        .remove(name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown policy `{}`. Available policies: {}", name, names))
+}
+
+impl PasswordPolicy {
+    /// 将策略合并到命令行的规则中，length 为 None 时使用策略的默认长度
+    /// 与策略冲突的命令行参数返回错误
+    pub fn apply(&self, rules: &mut PasswordRules, length: Option<usize>) -> Result<()> {
+        let max = self.max_length.unwrap_or(usize::MAX);
+        rules.length = match length {
+            Some(length) if length < self.min_length || length > max => anyhow::bail!(
+                "Length {} is outside the policy range {}..={}",
+                length,
+                self.min_length,
+                max
+            ),
+            Some(length) => length,
+            None => self
+                .length
+                .unwrap_or(rules.length)
+                .clamp(self.min_length, max),
+        };
+
+        for (name, rule, enabled) in [
+            ("upper", self.upper, &mut rules.upper),
+            ("lower", self.lower, &mut rules.lower),
+            ("digits", self.digits, &mut rules.number),
+            ("symbols", self.symbols, &mut rules.symbol),
+        ] {
+            match rule {
+                ClassRule::Required if !*enabled => {
+                    anyhow::bail!("The policy requires {} but they are disabled", name)
+                }
+                ClassRule::Forbidden => *enabled = false,
+                _ => {}
+            }
+        }
+
+        if let Some(allowed) = &self.symbol_set {
+            match &rules.symbols_set {
+                Some(set) if !set.chars().all(|c| allowed.contains(c)) => anyhow::bail!(
+                    "--symbols-set contains symbols the policy does not allow, allowed: {}",
+                    allowed
+                ),
+                Some(_) => {}
+                None => rules.symbols_set = Some(allowed.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    /// 返回密码违反的所有规则，为空时代表符合策略
+    pub fn check(&self, password: &str) -> Result<Vec<String>> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(format!("shorter than {} characters", self.min_length));
+        }
+        if let Some(max) = self.max_length.filter(|max| length > *max) {
+            violations.push(format!("longer than {} characters", max));
+        }
+
+        let symbol = |c: char| match &self.symbol_set {
+            Some(set) => set.contains(c),
+            None => c.is_ascii_punctuation(),
+        };
+        for (name, rule, found) in [
+            (
+                "upper",
+                self.upper,
+                password.chars().any(|c| c.is_ascii_uppercase()),
+            ),
+            (
+                "lower",
+                self.lower,
+                password.chars().any(|c| c.is_ascii_lowercase()),
+            ),
+            (
+                "digits",
+                self.digits,
+                password.chars().any(|c| c.is_ascii_digit()),
+            ),
+            ("symbols", self.symbols, password.chars().any(symbol)),
+        ] {
+            match rule {
+                ClassRule::Required if !found => violations.push(format!("missing {}", name)),
+                ClassRule::Forbidden if found => violations.push(format!("contains {}", name)),
+                _ => {}
+            }
+        }
+        if self.symbol_set.is_some()
+            && password
+                .chars()
+                .any(|c| c.is_ascii_punctuation() && !symbol(c))
+        {
+            violations.push("contains symbols outside the allowed set".to_string());
+        }
+
+        if let Some(max) = self.max_repeat {
+            if longest_run(password) > max {
+                violations.push(format!(
+                    "repeats a character more than {} times in a row",
+                    max
+                ));
+            }
+        }
+        for pattern in &self.forbidden {
+            if pattern.is_match(password) {
+                violations.push(format!("matches forbidden pattern `{}`", pattern));
+            }
+        }
+        Ok(violations)
+    }
+
+    /// 生成符合策略的密码，不符合时重新生成
+    pub fn generate(&self, rules: &PasswordRules) -> Result<String> {
+        let mut violations = Vec::new();
+        for _ in 0..MAX_ATTEMPTS {
+            let password = process_genpass(rules)?;
+            violations = self.check(&password)?;
+            if violations.is_empty() {
+                return Ok(password);
+            }
+        }
+        anyhow::bail!(
+            "Could not generate a password that satisfies the policy after {} attempts: {}",
+            MAX_ATTEMPTS,
+            violations.join(", ")
+        )
+    }
+}
+
+/// 同一个字符最长连续出现的次数
+fn longest_run(password: &str) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev = None;
+    for c in password.chars() {
+        run = if prev == Some(c) { run + 1 } else { 1 };
+        longest = longest.max(run);
+        prev = Some(c);
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_policies() -> Result<()> {
+        for name in ["aws-iam", "pci-dss", "nist-800-63b"] {
+            let policy = load_policy(name, None)?;
+            let mut rules = PasswordRules::default();
+            policy.apply(&mut rules, None)?;
+            let password = policy.generate(&rules)?;
+            assert!(policy.check(&password)?.is_empty());
+        }
+        assert!(load_policy("unknown", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_check() -> Result<()> {
+        let policy = load_policy("nist-800-63b", None)?;
+        let violations = policy.check("MyPassword111")?;
+        assert_eq!(violations.len(), 3);
+
+        let policy = load_policy("aws-iam", None)?;
+        assert!(policy.check("Abcdefg1!")?.is_empty());
+        assert_eq!(policy.check("abcdefg1~")?.len(), 3);
+
+        let mut rules = PasswordRules {
+            symbol: false,
+            ..Default::default()
+        };
+        assert!(policy.apply(&mut rules, None).is_err());
+        let mut rules = PasswordRules::default();
+        assert!(policy.apply(&mut rules, Some(6)).is_err());
+        Ok(())
+    }
+}
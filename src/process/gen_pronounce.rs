@@ -0,0 +1,100 @@
+use anyhow::Result;
+use rand::seq::SliceRandom;
+
+use super::gen_pass::PasswordRules;
+
+// 去掉了电话里容易听错的辅音 (c q w x y l)
+const CONSONANTS: &[u8] = b"bdfghjkmnprstvz";
+const VOWELS: &[u8] = b"aeiou";
+
+/// 生成由辅音 + 元音音节组成的密码，方便读出来，例如 "Takivomu47!"
+/// 启用数字 / 特殊字符时，按 --min-digits / --min-symbols (至少一个) 追加在字母后面
+/// 启用大写时只把首字母大写，位置固定，不增加熵；返回密码和熵 (bit)
+pub fn process_genpronounceable(rules: &PasswordRules) -> Result<(String, f64)> {
+    if !rules.upper && !rules.lower {
+        anyhow::bail!("Pronounceable passwords need upper or lower case letters");
+    }
+    let classes = rules.charsets()?;
+    let extras: Vec<_> = classes
+        .iter()
+        .filter(|c| c.name == "digits" || c.name == "symbols")
+        .collect();
+    let extra: usize = extras.iter().map(|c| c.min).sum();
+    let letters = rules.length.saturating_sub(extra);
+    if letters < 4 {
+        anyhow::bail!(
+            "Length {} is too short, pronounceable passwords need at least 4 letters besides {} digits and symbols",
+            rules.length,
+            extra
+        );
+    }
+
+    let allowed = |chars: &[u8]| -> Vec<u8> {
+        chars
+            .iter()
+            .copied()
+            .filter(|c| {
+                !rules.exclude.as_bytes().contains(c)
+                    && !rules.exclude.as_bytes().contains(&c.to_ascii_uppercase())
+            })
+            .collect()
+    };
+    let consonants = allowed(CONSONANTS);
+    let vowels = allowed(VOWELS);
+    if consonants.is_empty() || vowels.is_empty() {
+        anyhow::bail!("No consonants or vowels left after --exclude");
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut password = String::with_capacity(rules.length + 1);
+    let mut entropy = 0.0;
+    for i in 0..letters {
+        let set = if i % 2 == 0 { &consonants } else { &vowels };
+        let c = *set.choose(&mut rng).expect("set won't be empty") as char;
+        password.push(match (rules.upper, rules.lower) {
+            (true, false) => c.to_ascii_uppercase(),
+            (true, true) if i == 0 => c.to_ascii_uppercase(),
+            _ => c,
+        });
+        entropy += (set.len() as f64).log2();
+    }
+    for class in extras {
+        for _ in 0..class.min {
+            password.push(*class.chars.choose(&mut rng).expect("class won't be empty") as char);
+            entropy += (class.chars.len() as f64).log2();
+        }
+    }
+    Ok((password, entropy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_genpronounceable() -> Result<()> {
+        let rules = PasswordRules {
+            length: 12,
+            symbol: false,
+            min_digits: 2,
+            ..Default::default()
+        };
+        let (password, entropy) = process_genpronounceable(&rules)?;
+        let bytes = password.as_bytes();
+        assert_eq!(password.len(), 12);
+        assert!(bytes[0].is_ascii_uppercase());
+        assert!(VOWELS.contains(&bytes[1]));
+        assert!(CONSONANTS.contains(&bytes[2]));
+        assert!(bytes[10..].iter().all(u8::is_ascii_digit));
+        // 5 个辅音，5 个元音，2 个数字
+        let expected = 5.0 * 15f64.log2() + 5.0 * 5f64.log2() + 2.0 * 9f64.log2();
+        assert!((entropy - expected).abs() < 1e-9);
+
+        let short = PasswordRules {
+            length: 5,
+            ..Default::default()
+        };
+        assert!(process_genpronounceable(&short).is_err());
+        Ok(())
+    }
+}
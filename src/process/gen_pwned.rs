@@ -0,0 +1,134 @@
+use anyhow::Result;
+use sha1::{Digest, Sha1};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+};
+
+/// 剩余的范围小于这个值之后改为顺序查找
+const SCAN_BLOCK: u64 = 4096;
+
+/// 本地的 Have I Been Pwned 密码库，按 SHA1 排序的版本 (pwned-passwords-sha1-ordered-by-hash)
+/// 每行为 "SHA1:COUNT"，文件可能有几十 GB，因此不读进内存，而是在文件中二分查找
+/// 大写和小写的十六进制排序相同，因此两种格式的文件都可以使用
+#[derive(Debug)]
+pub struct PwnedDb {
+    file: File,
+    len: u64,
+}
+
+impl PwnedDb {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref()).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to open pwned passwords file {}: {}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+        let len = file.metadata()?.len();
+        Ok(Self { file, len })
+    }
+
+    /// 密码在泄露数据中出现的次数，没有出现时为 0
+    pub fn count(&self, password: &str) -> Result<u64> {
+        let hash: String = Sha1::digest(password.as_bytes())
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        self.lookup(&hash)
+    }
+
+    /// 二分查找时 lo 总是一行的开头，并且第一个 >= hash 的行从 hi 或者之前开始
+    fn lookup(&self, hash: &str) -> Result<u64> {
+        // &File 实现了 Read 和 Seek，查找不需要 &mut self
+        let mut reader = BufReader::new(&self.file);
+        let mut line = String::new();
+        let (mut lo, mut hi) = (0, self.len);
+        while hi - lo > SCAN_BLOCK {
+            let mid = lo + (hi - lo) / 2;
+            // 跳过 mid 所在的半行，读取下一个完整的行
+            reader.seek(SeekFrom::Start(mid - 1))?;
+            line.clear();
+            let start = mid - 1 + reader.read_line(&mut line)? as u64;
+            if start >= hi {
+                break;
+            }
+            line.clear();
+            let read = reader.read_line(&mut line)? as u64;
+            if read == 0 || line_hash(&line)?.as_str() >= hash {
+                hi = start;
+            } else {
+                lo = start + read;
+            }
+        }
+
+        reader.seek(SeekFrom::Start(lo))?;
+        let mut pos = lo;
+        while pos <= hi {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            pos += read as u64;
+            let found = line_hash(&line)?;
+            if found == hash {
+                let count = line.trim_end()[hash.len()..].trim_start_matches(':');
+                return count.parse().map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid count in line `{}` of pwned passwords file, expect SHA1:COUNT",
+                        line.trim_end()
+                    )
+                });
+            }
+            if found.as_str() > hash {
+                break;
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// 行中的 SHA1，统一转换成大写
+fn line_hash(line: &str) -> Result<String> {
+    let hash = line.split(':').next().unwrap_or_default().trim();
+    if hash.len() != 40 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!(
+            "Invalid line `{}` in pwned passwords file, expect SHA1:COUNT sorted by hash",
+            line.trim_end()
+        );
+    }
+    Ok(hash.to_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pwned_db() -> Result<()> {
+        let db = PwnedDb::open("fixtures/pwned_sample.txt")?;
+        assert_eq!(db.count("password")?, 9659365);
+        assert_eq!(db.count("hunter2")?, 24230);
+        assert_eq!(db.count("correct horse battery staple")?, 384);
+        assert_eq!(db.count("y1aKo#ssBC8i#*@C")?, 0);
+
+        // 文件中的每一行都能找到
+        for line in std::fs::read_to_string("fixtures/pwned_sample.txt")?.lines() {
+            let (hash, count) = line.split_once(':').unwrap();
+            assert_eq!(db.lookup(hash)?, count.parse::<u64>()?);
+        }
+
+        // 小写的十六进制同样可以查找，次数格式错误时返回错误
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pwned_lower.txt");
+        let content = std::fs::read_to_string("fixtures/pwned_sample.txt")?;
+        std::fs::write(&path, content.to_lowercase())?;
+        assert_eq!(PwnedDb::open(&path)?.count("hunter2")?, 24230);
+        std::fs::write(&path, content.replace(":24230", ":many"))?;
+        assert!(PwnedDb::open(&path)?.count("hunter2").is_err());
+        Ok(())
+    }
+}
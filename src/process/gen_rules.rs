@@ -0,0 +1,220 @@
+use anyhow::Result;
+
+use super::gen_policy::{ClassRule, PasswordPolicy};
+
+/// passwordrules 中 special 对应的字符 (去掉了空格)
+const SPECIAL: &str = "-~!@#$%^&*_+=`|(){}[:;\"'<>,.?]";
+
+/// 解析 Apple passwordrules 格式的规则，转换成 PasswordPolicy
+/// 例如 "minlength: 20; required: lower; required: upper; allowed: [-_];"
+///
+/// - required 中的每一项都会保证至少出现一次，`required: upper, lower` 也按两者都出现处理
+/// - 没有出现在 required / allowed 中的字符类不会使用
+/// - 自定义字符类 `[...]` 只支持特殊字符
+pub fn parse_passwordrules(rules: &str) -> Result<PasswordPolicy> {
+    let mut policy = PasswordPolicy {
+        description: rules.to_string(),
+        min_length: 1,
+        max_length: None,
+        length: None,
+        upper: ClassRule::Forbidden,
+        lower: ClassRule::Forbidden,
+        digits: ClassRule::Forbidden,
+        symbols: ClassRule::Forbidden,
+        symbol_set: None,
+        max_repeat: None,
+        forbidden: vec![],
+    };
+    let mut symbols = String::new();
+    let mut has_classes = false;
+
+    for property in rules.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+        let (name, value) = property.split_once(':').ok_or_else(|| {
+            anyhow::anyhow!("Invalid rule `{}`, expect <name>: <value>", property)
+        })?;
+        let value = value.trim();
+        match name.trim().to_lowercase().as_str() {
+            "minlength" => policy.min_length = parse_number(name, value)?,
+            "maxlength" => policy.max_length = Some(parse_number(name, value)?),
+            "max-consecutive" => {
+                let max = parse_number(name, value)?;
+                if max == 0 {
+                    anyhow::bail!("max-consecutive must be at least 1");
+                }
+                policy.max_repeat = Some(policy.max_repeat.map_or(max, |m| m.min(max)));
+            }
+            rule @ ("required" | "allowed") => {
+                let rule = if rule == "required" {
+                    ClassRule::Required
+                } else {
+                    ClassRule::Allowed
+                };
+                for class in parse_classes(value)? {
+                    has_classes = true;
+                    match class {
+                        Class::Upper => upgrade(&mut policy.upper, rule),
+                        Class::Lower => upgrade(&mut policy.lower, rule),
+                        Class::Digit => upgrade(&mut policy.digits, rule),
+                        Class::Special(chars) => {
+                            upgrade(&mut policy.symbols, rule);
+                            for c in chars.chars() {
+                                if !symbols.contains(c) {
+                                    symbols.push(c);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => anyhow::bail!(
+                "Unknown rule `{}`. Supported rules: minlength, maxlength, max-consecutive, required, allowed",
+                name.trim()
+            ),
+        }
+    }
+
+    // 没有指定任何字符类时，默认允许所有 ascii 可打印字符
+    if !has_classes {
+        for rule in [
+            &mut policy.upper,
+            &mut policy.lower,
+            &mut policy.digits,
+            &mut policy.symbols,
+        ] {
+            *rule = ClassRule::Allowed;
+        }
+        symbols = SPECIAL.to_string();
+    }
+    if !symbols.is_empty() {
+        policy.symbol_set = Some(symbols);
+    }
+
+    if let Some(max) = policy.max_length {
+        if max < policy.min_length {
+            anyhow::bail!(
+                "Unsatisfiable rules: minlength {} is greater than maxlength {}",
+                policy.min_length,
+                max
+            );
+        }
+        let required = [policy.upper, policy.lower, policy.digits, policy.symbols]
+            .iter()
+            .filter(|r| **r == ClassRule::Required)
+            .count();
+        if max < required {
+            anyhow::bail!(
+                "Unsatisfiable rules: {} character classes are required but maxlength is {}",
+                required,
+                max
+            );
+        }
+    }
+    Ok(policy)
+}
+
+enum Class {
+    Upper,
+    Lower,
+    Digit,
+    Special(String),
+}
+
+/// required / allowed 中逗号分隔的字符类，例如 "upper, [-_]"
+fn parse_classes(value: &str) -> Result<Vec<Class>> {
+    let mut classes = Vec::new();
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let item = if let Some(custom) = rest.strip_prefix('[') {
+            // 自定义字符类中可以包含逗号，因此单独处理，`]` 可以作为第一个字符
+            let end = custom
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| *c == ']')
+                .map(|(i, _)| i)
+                .ok_or_else(|| anyhow::anyhow!("Unclosed custom class in `{}`", value))?;
+            let chars = &custom[..end];
+            if chars
+                .chars()
+                .any(|c| c.is_alphanumeric() || c.is_whitespace())
+            {
+                anyhow::bail!(
+                    "Custom class [{}] is not supported, only special characters can be listed",
+                    chars
+                );
+            }
+            classes.push(Class::Special(chars.to_string()));
+            &custom[end + 1..]
+        } else {
+            let (name, tail) = rest.split_once(',').unwrap_or((rest, ""));
+            match name.trim().to_lowercase().as_str() {
+                "upper" => classes.push(Class::Upper),
+                "lower" => classes.push(Class::Lower),
+                "digit" => classes.push(Class::Digit),
+                "special" => classes.push(Class::Special(SPECIAL.to_string())),
+                // unicode 按 ascii 可打印字符处理，生成的密码仍然符合规则
+                "ascii-printable" | "unicode" => classes.extend([
+                    Class::Upper,
+                    Class::Lower,
+                    Class::Digit,
+                    Class::Special(SPECIAL.to_string()),
+                ]),
+                other => anyhow::bail!(
+                    "Unknown character class `{}`. Supported: upper, lower, digit, special, ascii-printable, unicode, [...]",
+                    other
+                ),
+            }
+            tail
+        };
+        rest = item.trim_start().trim_start_matches(',').trim_start();
+    }
+    Ok(classes)
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("{} expects a number, got `{}`", name.trim(), value))
+}
+
+/// 同一个字符类同时出现在 required 和 allowed 中时，以 required 为准
+fn upgrade(current: &mut ClassRule, rule: ClassRule) {
+    if *current != ClassRule::Required {
+        *current = rule;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::gen_pass::PasswordRules;
+
+    #[test]
+    fn test_parse_passwordrules() -> Result<()> {
+        let policy = parse_passwordrules(
+            "minlength: 20; required: lower; required: upper; required: digit; allowed: [-_];",
+        )?;
+        assert_eq!(policy.min_length, 20);
+        assert_eq!(policy.upper, ClassRule::Required);
+        assert_eq!(policy.symbols, ClassRule::Allowed);
+        assert_eq!(policy.symbol_set.as_deref(), Some("-_"));
+
+        let mut rules = PasswordRules::default();
+        policy.apply(&mut rules, None)?;
+        let password = policy.generate(&rules)?;
+        assert_eq!(password.len(), 20);
+        assert!(password
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_passwordrules_invalid() {
+        assert!(parse_passwordrules("minlength: 20; maxlength: 10").is_err());
+        assert!(parse_passwordrules("maxlength: 2; required: upper, lower, digit").is_err());
+        assert!(parse_passwordrules("required: emoji").is_err());
+        assert!(parse_passwordrules("allowed: [abc]").is_err());
+        assert!(parse_passwordrules("max-consecutive: 0").is_err());
+        assert!(parse_passwordrules("minlength 20").is_err());
+    }
+}
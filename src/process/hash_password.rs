@@ -0,0 +1,205 @@
+use anyhow::Result;
+use argon2::password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use pbkdf2::Pbkdf2;
+use rand::rngs::OsRng;
+use scrypt::Scrypt;
+
+use crate::cli::HashAlgorithm;
+
+/// bcrypt 只使用密码的前 72 个字节，更长的部分会被静默地忽略
+const BCRYPT_MAX_BYTES: usize = 72;
+
+/// 哈希参数，为 None 时使用各算法推荐的默认值 (OWASP Password Storage Cheat Sheet)
+#[derive(Debug, Default)]
+pub struct HashParams {
+    /// argon2id 的内存 (KiB)
+    pub memory_kib: Option<u32>,
+    /// argon2id 的迭代次数，或者 pbkdf2 的轮数
+    pub iterations: Option<u32>,
+    /// argon2id 和 scrypt 的并行度
+    pub parallelism: Option<u32>,
+    /// bcrypt 的 cost
+    pub cost: Option<u32>,
+    /// scrypt 的 log2(N) 和 r
+    pub log_n: Option<u8>,
+    pub block_size: Option<u32>,
+}
+
+/// 计算密码的哈希，argon2id / scrypt / pbkdf2 输出 PHC 字符串，bcrypt 输出 $2b$ 开头的 MCF 格式 (不是 PHC 字符串)
+pub fn process_hash_password(
+    password: &str,
+    algorithm: HashAlgorithm,
+    params: &HashParams,
+) -> Result<String> {
+    params.check(algorithm)?;
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = match algorithm {
+        HashAlgorithm::Argon2id => {
+            let params = argon2::Params::new(
+                params.memory_kib.unwrap_or(19 * 1024),
+                params.iterations.unwrap_or(2),
+                params.parallelism.unwrap_or(1),
+                None,
+            )
+            .map_err(|e| anyhow::anyhow!("Invalid argon2 parameters: {}", e))?;
+            Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                .hash_password(password.as_bytes(), &salt)?
+                .to_string()
+        }
+        HashAlgorithm::Bcrypt => {
+            if password.len() > BCRYPT_MAX_BYTES {
+                anyhow::bail!(
+                    "bcrypt only uses the first {} bytes of a password, got {} bytes; use a shorter password (e.g. -l 72 with --generate) or another algorithm",
+                    BCRYPT_MAX_BYTES,
+                    password.len()
+                );
+            }
+            bcrypt::hash(password, params.cost.unwrap_or(12))?
+        }
+        HashAlgorithm::Scrypt => {
+            let params = scrypt::Params::new(
+                params.log_n.unwrap_or(17),
+                params.block_size.unwrap_or(8),
+                params.parallelism.unwrap_or(1),
+                scrypt::Params::RECOMMENDED_LEN,
+            )
+            .map_err(|e| anyhow::anyhow!("Invalid scrypt parameters: {}", e))?;
+            Scrypt
+                .hash_password_customized(password.as_bytes(), None, None, params, &salt)?
+                .to_string()
+        }
+        HashAlgorithm::Pbkdf2 => {
+            let params = pbkdf2::Params {
+                rounds: params.iterations.unwrap_or(600_000),
+                output_length: 32,
+            };
+            Pbkdf2
+                .hash_password_customized(
+                    password.as_bytes(),
+                    Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                    None,
+                    params,
+                    &salt,
+                )?
+                .to_string()
+        }
+    };
+    Ok(hash)
+}
+
+/// 校验密码，根据哈希的格式自动识别算法
+pub fn process_verify_password(password: &str, hash: &str) -> Result<bool> {
+    let hash = hash.trim();
+    if ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+    {
+        return Ok(bcrypt::verify(password, hash)?);
+    }
+    let parsed = PasswordHash::new(hash)
+        .map_err(|e| anyhow::anyhow!("Invalid PHC string `{}`: {}", hash, e))?;
+    let verifiers: [&dyn PasswordVerifier; 3] = [&Argon2::default(), &Scrypt, &Pbkdf2];
+    match parsed.verify_password(&verifiers, password) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(password_hash::Error::Algorithm) => anyhow::bail!(
+            "Unsupported algorithm `{}`, supported: argon2id, argon2i, argon2d, scrypt, pbkdf2-sha256, pbkdf2-sha512, bcrypt",
+            parsed.algorithm
+        ),
+        Err(e) => anyhow::bail!("Failed to verify password: {}", e),
+    }
+}
+
+impl HashParams {
+    /// 不属于所选算法的参数直接报错，避免以为参数生效了
+    fn check(&self, algorithm: HashAlgorithm) -> Result<()> {
+        let supported: &[&str] = match algorithm {
+            HashAlgorithm::Argon2id => &["memory", "iterations", "parallelism"],
+            HashAlgorithm::Bcrypt => &["cost"],
+            HashAlgorithm::Scrypt => &["log-n", "block-size", "parallelism"],
+            HashAlgorithm::Pbkdf2 => &["iterations"],
+        };
+        for (name, set) in [
+            ("memory", self.memory_kib.is_some()),
+            ("iterations", self.iterations.is_some()),
+            ("parallelism", self.parallelism.is_some()),
+            ("cost", self.cost.is_some()),
+            ("log-n", self.log_n.is_some()),
+            ("block-size", self.block_size.is_some()),
+        ] {
+            if set && !supported.contains(&name) {
+                anyhow::bail!("--{} does not apply to {}", name, algorithm);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify_password() -> Result<()> {
+        // 测试中使用较小的参数，加快速度
+        let cases = [
+            (
+                HashAlgorithm::Argon2id,
+                HashParams {
+                    memory_kib: Some(64),
+                    iterations: Some(1),
+                    ..Default::default()
+                },
+                "$argon2id$",
+            ),
+            (
+                HashAlgorithm::Bcrypt,
+                HashParams {
+                    cost: Some(4),
+                    ..Default::default()
+                },
+                "$2b$04$",
+            ),
+            (
+                HashAlgorithm::Scrypt,
+                HashParams {
+                    log_n: Some(4),
+                    ..Default::default()
+                },
+                "$scrypt$",
+            ),
+            (
+                HashAlgorithm::Pbkdf2,
+                HashParams {
+                    iterations: Some(1000),
+                    ..Default::default()
+                },
+                "$pbkdf2-sha256$i=1000",
+            ),
+        ];
+        for (algorithm, params, prefix) in cases {
+            let hash = process_hash_password("hunter2", algorithm, &params)?;
+            assert!(hash.starts_with(prefix), "{}", hash);
+            assert!(process_verify_password("hunter2", &hash)?);
+            assert!(!process_verify_password("hunter3", &hash)?);
+        }
+
+        let params = HashParams {
+            cost: Some(10),
+            ..Default::default()
+        };
+        assert!(process_hash_password("hunter2", HashAlgorithm::Argon2id, &params).is_err());
+        assert!(process_verify_password("hunter2", "$md5$abc").is_err());
+
+        // bcrypt 拒绝超过 72 字节的密码，而不是截断
+        let params = HashParams {
+            cost: Some(4),
+            ..Default::default()
+        };
+        let long = format!("{}X", "a".repeat(72));
+        assert!(process_hash_password(&long, HashAlgorithm::Bcrypt, &params).is_err());
+        assert!(process_hash_password(&long[..72], HashAlgorithm::Bcrypt, &params).is_ok());
+        Ok(())
+    }
+}
@@ -1,11 +1,64 @@
 mod base64_convert;
+mod csv_batch;
 mod csv_convert;
+mod csv_crypto;
+mod csv_date;
+mod csv_diff;
+mod csv_fake;
+mod csv_layout;
+mod csv_mask;
+mod csv_reformat;
+mod csv_render;
+mod csv_sample;
+mod csv_watch;
+mod gen_batch;
+mod gen_check;
+mod gen_derive;
 mod gen_pass;
+mod gen_pattern;
+mod gen_phrase;
+mod gen_policy;
+mod gen_pronounce;
+mod gen_pwned;
+mod gen_rules;
+mod hash_password;
 mod http_serve;
+mod otp;
 mod text;
+mod vault;
 
 pub use base64_convert::{process_decode, process_encode};
-pub use csv_convert::process_csv;
-pub use gen_pass::process_genpass;
+pub use csv_batch::{
+    collect_batch_inputs, is_batch_input, process_csv_batch, BatchResult, BatchStatus,
+};
+pub use csv_convert::{process_csv, RecordWriter};
+pub use csv_crypto::{process_csv_decrypt, process_csv_encrypt};
+pub use csv_date::{check_date_format, parse_date, DateNormalizer, ParsedDate};
+pub use csv_diff::{process_csv_diff, CellChange, CsvDiff, RowChange};
+pub use csv_fake::{process_csv_fake, FakeColumn, FakeGenerator, FakeSchema};
+pub use csv_layout::{process_text_layout, FixedColumn, TextLayout};
+pub use csv_mask::process_csv_mask;
+pub use csv_reformat::{process_csv_reformat, ReformatOptions};
+pub use csv_render::process_csv_render;
+pub use csv_sample::{process_csv_sample, process_csv_slice};
+pub use csv_watch::process_csv_watch;
+pub use gen_batch::{
+    process_genbatch, write_passwords_csv, write_passwords_json, GeneratedPassword, StrengthTarget,
+};
+pub use gen_check::{check_password, process_genpass_check, CrackTimes, PasswordReport};
+pub use gen_derive::{process_genderive, DeriveOptions};
+pub use gen_pass::{process_genpass, PasswordRules};
+pub use gen_pattern::{process_genpattern, process_genpin};
+pub use gen_phrase::{load_wordlist, process_genphrase, PassphraseOptions};
+pub use gen_policy::{load_policy, ClassRule, PasswordPolicy};
+pub use gen_pronounce::process_genpronounceable;
+pub use gen_pwned::PwnedDb;
+pub use gen_rules::parse_passwordrules;
+pub use hash_password::{process_hash_password, process_verify_password, HashParams};
 pub use http_serve::process_http_serve;
+pub use otp::{
+    process_hotp, process_hotp_verify, process_otp_secret, process_otp_uri, process_totp,
+    process_totp_verify, render_qr, OtpParams, MAX_OTP_WINDOW,
+};
 pub use text::{process_text_generate, process_text_sign, process_text_verify};
+pub use vault::{process_vault_export, KdfParams, Vault, VaultEntry};
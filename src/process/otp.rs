@@ -0,0 +1,273 @@
+use anyhow::Result;
+use base32::Alphabet;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use qrcode::{render::unicode, QrCode};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::cli::OtpAlgorithm;
+
+/// RFC 4648 base32，不带 padding，与 Google Authenticator 等应用一致
+const SECRET_ALPHABET: Alphabet = Alphabet::Rfc4648 { padding: false };
+
+/// otpauth URI 中需要编码的字符，保留 RFC 3986 的 unreserved 字符
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// 校验时允许的最大窗口，过大的窗口既慢又会大幅降低安全性
+pub const MAX_OTP_WINDOW: u64 = 100;
+
+/// HOTP / TOTP 的参数，需要与验证方 (例如 Authenticator 应用) 保持一致
+#[derive(Debug, Clone, Copy)]
+pub struct OtpParams {
+    pub algorithm: OtpAlgorithm,
+    /// 验证码的位数 (6-10)
+    pub digits: u32,
+    /// TOTP 的时间步长 (秒)
+    pub period: u64,
+}
+
+impl Default for OtpParams {
+    fn default() -> Self {
+        Self {
+            algorithm: OtpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+        }
+    }
+}
+
+/// 生成 base32 编码的随机密钥，RFC 4226 建议至少 160 bit (20 字节)
+pub fn process_otp_secret(bytes: usize) -> String {
+    let mut secret = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut secret);
+    base32::encode(SECRET_ALPHABET, &secret)
+}
+
+/// RFC 4226 HOTP
+pub fn process_hotp(secret: &str, counter: u64, params: &OtpParams) -> Result<String> {
+    if !(6..=10).contains(&params.digits) {
+        anyhow::bail!("Digits must be between 6 and 10, got {}", params.digits);
+    }
+    let key = decode_secret(secret)?;
+    let msg = counter.to_be_bytes();
+    let hash = match params.algorithm {
+        OtpAlgorithm::Sha1 => hmac::<Hmac<Sha1>>(&key, &msg)?,
+        OtpAlgorithm::Sha256 => hmac::<Hmac<Sha256>>(&key, &msg)?,
+        OtpAlgorithm::Sha512 => hmac::<Hmac<Sha512>>(&key, &msg)?,
+    };
+    // dynamic truncation: 最后一个字节的低 4 位作为偏移，取 31 bit
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let bin = u32::from_be_bytes(hash[offset..offset + 4].try_into()?) & 0x7fff_ffff;
+    let code = bin as u64 % 10u64.pow(params.digits);
+    Ok(format!("{:0width$}", code, width = params.digits as usize))
+}
+
+/// RFC 6238 TOTP，timestamp 为 Unix 时间 (秒)
+pub fn process_totp(secret: &str, timestamp: u64, params: &OtpParams) -> Result<String> {
+    if params.period == 0 {
+        anyhow::bail!("Period must be greater than 0");
+    }
+    process_hotp(secret, timestamp / params.period, params)
+}
+
+/// 在前后 window 个时间步内校验 TOTP，匹配时返回相对当前时间步的偏移
+pub fn process_totp_verify(
+    secret: &str,
+    code: &str,
+    timestamp: u64,
+    window: u64,
+    params: &OtpParams,
+) -> Result<Option<i64>> {
+    if params.period == 0 {
+        anyhow::bail!("Period must be greater than 0");
+    }
+    check_window(window)?;
+    let code = normalize_code(code);
+    let step = timestamp / params.period;
+    for counter in step.saturating_sub(window)..=step.saturating_add(window) {
+        if process_hotp(secret, counter, params)? == code {
+            return Ok(Some(counter as i64 - step as i64));
+        }
+    }
+    Ok(None)
+}
+
+/// 在 counter 之后的 window 个计数内校验 HOTP (只向前查找)，匹配时返回匹配的计数，验证方应更新为它加一
+pub fn process_hotp_verify(
+    secret: &str,
+    code: &str,
+    counter: u64,
+    window: u64,
+    params: &OtpParams,
+) -> Result<Option<u64>> {
+    check_window(window)?;
+    let code = normalize_code(code);
+    for counter in counter..=counter.saturating_add(window) {
+        if process_hotp(secret, counter, params)? == code {
+            return Ok(Some(counter));
+        }
+    }
+    Ok(None)
+}
+
+/// Key URI Format: otpauth://TYPE/ISSUER:ACCOUNT?secret=...，counter 为 Some 时为 HOTP
+pub fn process_otp_uri(
+    secret: &str,
+    account: &str,
+    issuer: Option<&str>,
+    counter: Option<u64>,
+    params: &OtpParams,
+) -> Result<String> {
+    // 提前校验密钥，避免生成无法使用的二维码
+    decode_secret(secret)?;
+    let secret = normalize_secret(secret);
+    let encode = |s: &str| utf8_percent_encode(s, URI_COMPONENT).to_string();
+    let kind = if counter.is_some() { "hotp" } else { "totp" };
+    let label = match issuer {
+        Some(issuer) => format!("{}:{}", encode(issuer), encode(account)),
+        None => encode(account),
+    };
+    let mut uri = format!("otpauth://{}/{}?secret={}", kind, label, secret);
+    if let Some(issuer) = issuer {
+        uri.push_str(&format!("&issuer={}", encode(issuer)));
+    }
+    uri.push_str(&format!(
+        "&algorithm={}&digits={}",
+        params.algorithm.to_string().to_uppercase(),
+        params.digits
+    ));
+    match counter {
+        Some(counter) => uri.push_str(&format!("&counter={}", counter)),
+        None => uri.push_str(&format!("&period={}", params.period)),
+    }
+    Ok(uri)
+}
+
+/// 在终端中显示的二维码，浅色模块用空格，适合深色背景的终端
+pub fn render_qr(data: &str) -> Result<String> {
+    let code = QrCode::new(data.as_bytes())?;
+    Ok(code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build())
+}
+
+/// 允许小写、空格和 padding，例如 "jbsw y3dp ehpk 3pxp"
+fn normalize_secret(secret: &str) -> String {
+    secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=' && *c != '-')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn decode_secret(secret: &str) -> Result<Vec<u8>> {
+    let key = base32::decode(SECRET_ALPHABET, &normalize_secret(secret))
+        .ok_or_else(|| anyhow::anyhow!("Invalid base32 secret"))?;
+    if key.is_empty() {
+        anyhow::bail!("Secret must not be empty");
+    }
+    Ok(key)
+}
+
+fn check_window(window: u64) -> Result<()> {
+    if window > MAX_OTP_WINDOW {
+        anyhow::bail!("Window must be at most {}, got {}", MAX_OTP_WINDOW, window);
+    }
+    Ok(())
+}
+
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], msg: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key)
+        .map_err(|e| anyhow::anyhow!("Invalid HMAC key: {}", e))?;
+    mac.update(msg);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(algorithm: OtpAlgorithm, digits: u32) -> OtpParams {
+        OtpParams {
+            algorithm,
+            digits,
+            period: 30,
+        }
+    }
+
+    #[test]
+    fn test_rfc_vectors() -> Result<()> {
+        // RFC 4226 附录 D
+        let secret = base32::encode(SECRET_ALPHABET, b"12345678901234567890");
+        let hotp = ["755224", "287082", "359152", "969429", "338314"];
+        for (counter, code) in hotp.iter().enumerate() {
+            let p = params(OtpAlgorithm::Sha1, 6);
+            assert_eq!(process_hotp(&secret, counter as u64, &p)?, *code);
+        }
+
+        // RFC 6238 附录 B，不同算法使用不同长度的密钥
+        let cases = [
+            (
+                OtpAlgorithm::Sha1,
+                20,
+                [(59, "94287082"), (1111111109, "07081804")],
+            ),
+            (
+                OtpAlgorithm::Sha256,
+                32,
+                [(59, "46119246"), (1111111109, "68084774")],
+            ),
+            (
+                OtpAlgorithm::Sha512,
+                64,
+                [(59, "90693936"), (1111111109, "25091201")],
+            ),
+        ];
+        for (algorithm, len, vectors) in cases {
+            let key: Vec<u8> = b"1234567890".iter().cycle().take(len).copied().collect();
+            let secret = base32::encode(SECRET_ALPHABET, &key);
+            for (time, code) in vectors {
+                assert_eq!(process_totp(&secret, time, &params(algorithm, 8))?, code);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_and_uri() -> Result<()> {
+        let secret = process_otp_secret(20);
+        assert_eq!(secret.len(), 32);
+        let p = OtpParams::default();
+        let code = process_totp(&secret, 1_000_000, &p)?;
+        assert_eq!(
+            process_totp_verify(&secret, &code, 1_000_030, 1, &p)?,
+            Some(-1)
+        );
+        assert_eq!(process_totp_verify(&secret, &code, 1_000_060, 1, &p)?, None);
+
+        let code = process_hotp(&secret, 5, &p)?;
+        assert_eq!(process_hotp_verify(&secret, &code, 3, 2, &p)?, Some(5));
+        assert_eq!(process_hotp_verify(&secret, &code, 6, 10, &p)?, None);
+        assert!(process_hotp_verify(&secret, &code, 0, u64::MAX, &p).is_err());
+
+        let uri = process_otp_uri("jbsw y3dp", "alice@example.com", Some("ACME Co"), None, &p)?;
+        assert_eq!(
+            uri,
+            "otpauth://totp/ACME%20Co:alice%40example.com?secret=JBSWY3DP&issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30"
+        );
+        assert!(process_otp_uri("not base32!", "alice", None, None, &p).is_err());
+        Ok(())
+    }
+}
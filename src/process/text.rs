@@ -1,4 +1,4 @@
-use crate::{cli::TextSignFormat, process_genpass, utils::get_read};
+use crate::{cli::TextSignFormat, process_genpass, utils::get_read, PasswordRules};
 use anyhow::{Ok, Result};
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, prelude::*};
@@ -6,7 +6,7 @@ use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use rand::rngs::OsRng;
 use std::{fs, io::Read, path::Path};
 /// 定义签名的Trait
-trait TextSign {
+pub(crate) trait TextSign {
     /// sign the data form the reader and return the signature
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
 }
@@ -18,7 +18,7 @@ trait TextVerify {
 }
 
 /// 读取文件路径下的key trait(特征)
-trait KeyLoader {
+pub(crate) trait KeyLoader {
     fn load(path: impl AsRef<Path>) -> Result<Self>
     where
         Self: Sized; // 使用Sized 来约束这个Trait, 必须是一个有固定长度的result  not str, [u8]
@@ -29,7 +29,7 @@ pub trait KeyGenerator {
     fn generate() -> Result<Vec<Vec<u8>>>;
 }
 
-struct Blake3 {
+pub(crate) struct Blake3 {
     key: [u8; 32],
 }
 
@@ -163,7 +163,10 @@ impl KeyLoader for Blake3 {
 impl KeyGenerator for Blake3 {
     fn generate() -> Result<Vec<Vec<u8>>> {
         // 因为 blake3 只需要一个key，这里直接调用 genpass 方案。
-        let key = process_genpass(32, true, true, true, true)?;
+        let key = process_genpass(&PasswordRules {
+            length: 32,
+            ..Default::default()
+        })?;
         let key = key.as_bytes().to_vec(); // 这里可能有性能问题，暂时先不考虑
         Ok(vec![key])
     }
@@ -0,0 +1,324 @@
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, prelude::*};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{cli::OutputFormat, utils::get_writer};
+
+/// 当前的文件格式版本
+const VAULT_VERSION: u32 = 1;
+
+/// 密码库文件中明文的部分：KDF 参数、nonce 和密文
+/// 除了 nonce 和密文之外的字段作为 AEAD 的附加数据，篡改之后无法解密
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Argon2id 参数，salt 在 init 时随机生成，之后保持不变
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    #[serde(default)]
+    salt: String,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+            salt: String::new(),
+        }
+    }
+}
+
+/// 密码库中的一条记录，site + user 唯一
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultEntry {
+    pub site: String,
+    pub user: String,
+    pub password: String,
+    pub created: DateTime<Utc>,
+    pub rotated: Option<DateTime<Utc>>,
+}
+
+/// 打开之后的密码库，修改之后需要调用 save 写回文件
+pub struct Vault {
+    path: PathBuf,
+    kdf: KdfParams,
+    cipher: XChaCha20Poly1305,
+    pub entries: Vec<VaultEntry>,
+}
+
+impl Vault {
+    /// 创建新的密码库，文件已经存在时返回错误，避免覆盖
+    pub fn init(path: impl AsRef<Path>, master: &str, mut kdf: KdfParams) -> Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            anyhow::bail!("Vault {} already exists", path.display());
+        }
+        if master.is_empty() {
+            anyhow::bail!("Master password must not be empty");
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        kdf.salt = STANDARD.encode(salt);
+        let vault = Self {
+            path: path.to_path_buf(),
+            cipher: derive_cipher(master, &kdf)?,
+            kdf,
+            entries: Vec::new(),
+        };
+        vault.save()?;
+        Ok(vault)
+    }
+
+    pub fn open(path: impl AsRef<Path>, master: &str) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to read vault {}: {}, run `rcli vault init` first",
+                path.display(),
+                e
+            )
+        })?;
+        let file: VaultFile = serde_json::from_str(&content)?;
+        if file.version != VAULT_VERSION {
+            anyhow::bail!("Unsupported vault version {}", file.version);
+        }
+        let cipher = derive_cipher(master, &file.kdf)?;
+        let nonce = STANDARD.decode(&file.nonce)?;
+        if nonce.len() != 24 {
+            anyhow::bail!("Invalid vault nonce");
+        }
+        let payload = Payload {
+            msg: &STANDARD.decode(&file.ciphertext)?,
+            aad: &associated_data(&file.kdf)?,
+        };
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(&nonce), payload)
+            .map_err(|_| {
+                anyhow::anyhow!("Failed to open vault: wrong master password or tampered file")
+            })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            kdf: file.kdf,
+            cipher,
+            entries: serde_json::from_slice(&plaintext)?,
+        })
+    }
+
+    /// 每次保存使用新的 nonce，先写临时文件再重命名，避免写到一半时损坏密码库
+    pub fn save(&self) -> Result<()> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(&self.entries)?;
+        let payload = Payload {
+            msg: &plaintext,
+            aad: &associated_data(&self.kdf)?,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt vault"))?;
+        let file = VaultFile {
+            version: VAULT_VERSION,
+            kdf: self.kdf.clone(),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        };
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut writer = options.open(&tmp)?;
+        // mode 只在创建文件时生效，残留的 .tmp 文件需要显式修改权限
+        #[cfg(unix)]
+        writer.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        writer.write_all(serde_json::to_string_pretty(&file)?.as_bytes())?;
+        writer.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, site: &str, user: &str, password: String) -> Result<()> {
+        if self.find(site, Some(user)).is_ok() {
+            anyhow::bail!(
+                "{} @ {} already exists, use `rcli vault rotate` to change it",
+                user,
+                site
+            );
+        }
+        self.entries.push(VaultEntry {
+            site: site.to_string(),
+            user: user.to_string(),
+            password,
+            created: Utc::now(),
+            rotated: None,
+        });
+        Ok(())
+    }
+
+    /// user 为 None 时，site 下只能有一条记录
+    pub fn get(&self, site: &str, user: Option<&str>) -> Result<&VaultEntry> {
+        Ok(&self.entries[self.find(site, user)?])
+    }
+
+    pub fn remove(&mut self, site: &str, user: Option<&str>) -> Result<VaultEntry> {
+        let idx = self.find(site, user)?;
+        Ok(self.entries.remove(idx))
+    }
+
+    pub fn rotate(&mut self, site: &str, user: Option<&str>, password: String) -> Result<()> {
+        let idx = self.find(site, user)?;
+        let entry = &mut self.entries[idx];
+        entry.password = password;
+        entry.rotated = Some(Utc::now());
+        Ok(())
+    }
+
+    fn find(&self, site: &str, user: Option<&str>) -> Result<usize> {
+        let matches: Vec<usize> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.site == site && user.is_none_or(|u| e.user == u))
+            .map(|(i, _)| i)
+            .collect();
+        match matches.as_slice() {
+            [idx] => Ok(*idx),
+            [] => anyhow::bail!("No entry for {} @ {}", user.unwrap_or("*"), site),
+            _ => {
+                let users = matches
+                    .iter()
+                    .map(|i| self.entries[*i].user.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                anyhow::bail!(
+                    "{} has several users ({}), specify one with --user",
+                    site,
+                    users
+                )
+            }
+        }
+    }
+}
+
+/// 导出所有记录 (包含明文密码)，与 save 一样只有所有者可以读写导出的文件
+pub fn process_vault_export(
+    entries: &[VaultEntry],
+    output: &str,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut writer: Box<dyn Write> = if output == "-" {
+        get_writer(output)?
+    } else {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(output)?;
+        // mode 只在创建文件时生效，覆盖已有的文件时也收紧权限
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        Box::new(file)
+    };
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, entries)?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Yaml => serde_yaml::to_writer(&mut writer, entries)?,
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for entry in entries {
+                writer.serialize(entry)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn derive_cipher(master: &str, kdf: &KdfParams) -> Result<XChaCha20Poly1305> {
+    let salt = STANDARD.decode(&kdf.salt)?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(master.as_bytes(), &salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive the vault key: {}", e))?;
+    Ok(XChaCha20Poly1305::new_from_slice(&key)?)
+}
+
+fn associated_data(kdf: &KdfParams) -> Result<Vec<u8>> {
+    let mut aad = format!("rcli-vault-v{}", VAULT_VERSION).into_bytes();
+    aad.extend(serde_json::to_vec(kdf)?);
+    Ok(aad)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_round_trip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("test.vault");
+        // 测试中使用较小的内存，加快速度
+        let kdf = KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            ..Default::default()
+        };
+        let mut vault = Vault::init(&path, "master", kdf.clone())?;
+        assert!(Vault::init(&path, "master", kdf).is_err());
+        vault.add("db.local", "admin", "s3cret".into())?;
+        vault.add("db.local", "readonly", "r34d".into())?;
+        assert!(vault.add("db.local", "admin", "again".into()).is_err());
+        vault.save()?;
+        assert!(!fs::read_to_string(&path)?.contains("s3cret"));
+
+        let mut vault = Vault::open(&path, "master")?;
+        assert_eq!(vault.get("db.local", Some("admin"))?.password, "s3cret");
+        // 有多个用户时需要指定 user
+        assert!(vault.get("db.local", None).is_err());
+        vault.rotate("db.local", Some("admin"), "n3w".into())?;
+        assert!(vault.get("db.local", Some("admin"))?.rotated.is_some());
+        vault.remove("db.local", Some("readonly"))?;
+        assert_eq!(vault.get("db.local", None)?.password, "n3w");
+
+        assert!(Vault::open(&path, "wrong").is_err());
+
+        let export = dir.path().join("export.json");
+        fs::write(&export, "")?;
+        process_vault_export(&vault.entries, export.to_str().unwrap(), OutputFormat::Json)?;
+        assert!(fs::read_to_string(&export)?.contains("n3w"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&export)?.permissions().mode() & 0o777, 0o600);
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
 use anyhow::Result;
-use std::{fs::File, io::Read};
+use std::{
+    fs::File,
+    io::{Read, Write},
+};
 
 /// 工具包
 /// input stdin or file_path
@@ -14,3 +17,13 @@ pub fn get_read(input: &str) -> Result<Box<dyn Read>> {
     };
     Ok(reader)
 }
+
+/// output stdout or file_path
+pub fn get_writer(output: &str) -> Result<Box<dyn Write>> {
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(File::create(output)?)
+    };
+    Ok(writer)
+}